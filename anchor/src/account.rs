@@ -1,18 +1,27 @@
 use std::ops::{Deref, DerefMut};
 
-use crate::{prelude::AnchorDeserialize, ToAccountInfos, ToAccountMetas};
-use crate::{Owner, ToAccountInfo};
+use crate::{AccountDeserialize, ToAccountInfos, ToAccountMetas};
+use crate::{Owner, SetAccountInfo, ToAccountInfo};
 use otter_solana_program::{
     account_info::AccountInfo, error::Error, instruction::AccountMeta, pubkey::Pubkey, Key, Result,
 };
 
 #[derive(Debug, Clone)]
-#[cfg_attr(any(kani, feature = "kani"), derive(kani::Arbitrary))]
 pub struct Account<'info, T> {
     pub account: T,
     pub info: AccountInfo<'info>,
 }
 
+/// Like anchor, only accounts holding data written as a `T` are accepted.
+#[cfg(any(kani, feature = "kani"))]
+impl<'info, T: kani::Arbitrary + AccountDeserialize> kani::Arbitrary for Account<'info, T> {
+    fn any() -> Self {
+        let info: AccountInfo<'info> = kani::any();
+        kani::assume(T::discriminator().is_none_or(|d| *info.discriminator == d));
+        Self::new(info, kani::any())
+    }
+}
+
 impl<'a, T> Account<'a, T> {
     pub fn new(info: AccountInfo<'a>, account: T) -> Account<'a, T> {
         Self { info, account }
@@ -35,7 +44,15 @@ impl<'a, T> Account<'a, T> {
     }
 }
 
-impl<'a, T: AnchorDeserialize + Owner> Account<'a, T> {
+impl<'a, T: AccountDeserialize> Account<'a, T> {
+    /// Whether the data of `info` was written as a `T`, which the tag of the
+    /// info stands for.
+    pub fn check_discriminator(info: &AccountInfo) -> bool {
+        T::discriminator().is_none_or(|d| *info.discriminator == d)
+    }
+}
+
+impl<'a, T: AccountDeserialize + Owner> Account<'a, T> {
     #[inline(never)]
     pub fn try_from(info: &AccountInfo<'a>) -> Result<Account<'a, T>> {
         if
@@ -47,7 +64,11 @@ impl<'a, T: AnchorDeserialize + Owner> Account<'a, T> {
         if info.owner != &T::owner() {
             return Err(Error::AccountDidNotDeserialize);
         }
-        let mut data: &[u8] = info.try_borrow_data()?;
+        if !Self::check_discriminator(info) {
+            return Err(Error::AccountDiscriminatorMismatch);
+        }
+        let data = info.try_borrow_data()?;
+        let mut data: &[u8] = &data;
         Ok(Account::new(
             info.clone(),
             T::deserialize(&mut data).map_err(|_| Error::AccountDidNotDeserialize)?,
@@ -77,6 +98,12 @@ impl<'info, T> ToAccountInfo<'info> for Account<'info, T> {
     }
 }
 
+impl<'info, T> SetAccountInfo<'info> for Account<'info, T> {
+    fn set_account_info(&mut self, info: AccountInfo<'info>) {
+        self.info = info;
+    }
+}
+
 impl<'info, T> ToAccountInfos<'info> for Account<'info, T> {
     fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
         vec![self.info.clone()]
@@ -116,7 +143,7 @@ impl<'info, T> Key for Account<'info, T> {
 
 impl<'info, T> TryFrom<&AccountInfo<'info>> for Account<'info, T>
 where
-    T: AnchorDeserialize + Owner,
+    T: AccountDeserialize + Owner,
 {
     type Error = Error;
 
@@ -124,3 +151,67 @@ where
         Self::try_from(info)
     }
 }
+
+/// Whether one of `lists` holds `name`, at compile time. The harnesses use it
+/// to check the fields named by `#[transitions]` on an instruction have a
+/// transition table.
+#[doc(hidden)]
+pub const fn __lists_contain(lists: &[&[&str]], name: &str) -> bool {
+    let name = name.as_bytes();
+    let mut l = 0;
+    while l < lists.len() {
+        let mut n = 0;
+        while n < lists[l].len() {
+            let other = lists[l][n].as_bytes();
+            let mut i = 0;
+            while i < name.len() && other.len() == name.len() && other[i] == name[i] {
+                i += 1;
+            }
+            if other.len() == name.len() && i == name.len() {
+                return true;
+            }
+            n += 1;
+        }
+        l += 1;
+    }
+    false
+}
+
+/// An account type of the program, as used in `Account` fields.
+pub struct AccountType {
+    /// `std::any::type_name` of the type.
+    pub type_name: &'static str,
+    pub discriminator: Option<[u8; 8]>,
+    /// `Account::check_discriminator` of the type.
+    pub accepts: fn(&AccountInfo) -> bool,
+}
+
+/// Checks no `Account` field of one of `types` can be populated from data
+/// written as another one. Types are told apart by their discriminators, and
+/// two types sharing one, e.g. types of the same name in different modules,
+/// accept each other's data. Types without a discriminator, e.g. accounts
+/// of other programs, are told apart by their owner instead and are skipped.
+#[cfg(any(kani, feature = "kani"))]
+pub fn verify_no_type_cosplay(types: &[std::vec::Vec<AccountType>]) {
+    let all = types.iter().flatten().collect::<std::vec::Vec<_>>();
+    for a in all.iter().filter(|a| a.discriminator.is_some()) {
+        for b in all.iter() {
+            let Some(discriminator) = b.discriminator else {
+                continue;
+            };
+            if a.discriminator == Some(discriminator) {
+                kani::assert(
+                    a.type_name == b.type_name,
+                    "Type cosplay: two account types share a discriminator",
+                );
+                continue;
+            }
+            let info: AccountInfo = kani::any();
+            info.set_discriminator(discriminator);
+            kani::assert(
+                !(a.accepts)(&info),
+                "Type cosplay: an `Account` field accepted data written as another type",
+            );
+        }
+    }
+}
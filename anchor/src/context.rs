@@ -1,6 +1,13 @@
 use std::marker::PhantomData;
 
-use otter_solana_program::{account_info::AccountInfo, pubkey::Pubkey, vec::fast::Vec};
+use otter_solana_program::{
+    account_info::AccountInfo,
+    instruction::Instruction,
+    program::{invoke_signed_with, ProgramResult},
+    pubkey::Pubkey,
+    vec::fast::Vec,
+    Result,
+};
 
 use crate::{ToAccountInfos, ToAccountMetas};
 
@@ -143,6 +150,27 @@ where
         self
     }
 
+    /// Runs the CPI of a helper to the program of this context, with its
+    /// accounts and signer seeds, the way `invoke_signed` does. `instruction`
+    /// is built by the helper, `known_ids` are the programs the helper is
+    /// written for, see `check_cpi_target`, and `process` stands for them.
+    pub fn invoke(
+        &self,
+        mut instruction: Instruction,
+        known_ids: &[Pubkey],
+        process: impl FnOnce(&Instruction, &[AccountInfo]) -> ProgramResult,
+    ) -> Result<()> {
+        instruction.program_id = *self.program.key;
+        invoke_signed_with(
+            &instruction,
+            &self.accounts.to_account_infos(),
+            self.signer_seeds,
+            known_ids,
+            |instruction, account_infos, _| process(instruction, account_infos),
+        )?;
+        Ok(())
+    }
+
     #[must_use]
     pub fn with_remaining_accounts(mut self, ra: Vec<AccountInfo<'info>>) -> Self {
         self.remaining_accounts = ra;
@@ -13,7 +13,6 @@ use crate::{
 };
 
 #[derive(Clone)]
-#[cfg_attr(any(kani, feature = "kani"), derive(kani::Arbitrary))]
 pub struct Interface<'info, T>(Program<'info, T>);
 
 impl<'a, T> Interface<'a, T> {
@@ -26,6 +25,17 @@ impl<'a, T> Interface<'a, T> {
     }
 }
 
+/// Like anchor, only accounts of one of the programs implementing `T` are
+/// accepted.
+#[cfg(any(kani, feature = "kani"))]
+impl<'info, T: crate::Ids> kani::Arbitrary for Interface<'info, T> {
+    fn any() -> Self {
+        let info: AccountInfo<'info> = kani::any();
+        kani::assume(T::ids().contains(info.key));
+        Self::new(info)
+    }
+}
+
 impl<'info, T> ToAccountInfo<'info> for Interface<'info, T> {
     fn to_account_info(&self) -> AccountInfo<'info> {
         self.0.to_account_info()
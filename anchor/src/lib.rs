@@ -6,6 +6,7 @@ pub mod account;
 pub mod context;
 pub mod interface;
 pub mod program;
+pub mod seeds;
 pub mod signer;
 pub mod system_program;
 pub mod sysvar;
@@ -29,20 +30,21 @@ pub mod prelude {
     };
 
     pub use otter_solana_macro::{
-        access_control, account, declare_id, error_code, helper_fn, invariant, program, Accounts,
-        InitSpace,
+        access_control, account, cpi_contract, declare_id, error_code, helper_fn, invariant,
+        program, Accounts, BoundedArbitrary, InitSpace,
     };
 
     pub use crate::account::{self, Account};
     pub use crate::context::{self, Context, CpiContext};
     pub use crate::interface::{Interface, InterfaceAccount};
     pub use crate::program::Program;
+    pub use crate::seeds;
     pub use crate::signer::{self, Signer};
 
     pub use super::{
-        err, require, require_eq, require_gte, require_keys_eq, require_keys_neq,
-        AccountDeserialize, AccountSerialize, Accounts, AccountsClose, AccountsExit, Id, Owner,
-        Space, ToAccountInfo, ToAccountInfos, ToAccountMetas,
+        err, nondet_stub, require, require_eq, require_gte, require_keys_eq, require_keys_neq,
+        AccountDeserialize, AccountSerialize, Accounts, AccountsClose, AccountsExit, Discriminator,
+        Id, Ids, Owner, SetAccountInfo, Space, ToAccountInfo, ToAccountInfos, ToAccountMetas,
     };
     pub use crate::system_program::{self, System};
     pub use crate::sysvar::Sysvar;
@@ -162,6 +164,27 @@ macro_rules! require_gte {
     };
 }
 
+/// Defines a nondeterministic replacement for an expensive function, to be
+/// used with `#[verify_stub(original, replacement)]`. The stub returns an
+/// arbitrary value satisfying the postcondition, in which the return value is
+/// bound to the given name.
+///
+/// ```ignore
+/// nondet_stub!(pub fn sqrt_stub(x: u128) -> u128, ensures |r| r * r <= x);
+/// ```
+#[macro_export]
+macro_rules! nondet_stub {
+    ($vis:vis fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty, ensures |$result:ident| $post:expr $(,)?) => {
+        #[cfg(any(kani, feature = "kani"))]
+        #[allow(unused_variables)]
+        $vis fn $name($($arg: $ty),*) -> $ret {
+            let $result: $ret = kani::any();
+            kani::assume($post);
+            $result
+        }
+    };
+}
+
 /// Transformation to an `AccountInfo` struct.
 pub trait ToAccountInfo<'info> {
     fn to_account_info(&self) -> AccountInfo<'info>;
@@ -173,6 +196,18 @@ impl<'info> ToAccountInfo<'info> for AccountInfo<'info> {
     }
 }
 
+/// Replacement of the `AccountInfo` of an account field, which makes it the
+/// same account as the field the info comes from.
+pub trait SetAccountInfo<'info> {
+    fn set_account_info(&mut self, info: AccountInfo<'info>);
+}
+
+impl<'info> SetAccountInfo<'info> for AccountInfo<'info> {
+    fn set_account_info(&mut self, info: AccountInfo<'info>) {
+        *self = info;
+    }
+}
+
 // impl<'info, T> ToAccountInfo<'info> for T
 // where
 //     T: AsRef<AccountInfo<'info>>,
@@ -191,6 +226,12 @@ pub trait AccountSerialize: crate::prelude::AnchorSerialize {
 }
 
 pub trait AccountDeserialize: crate::prelude::AnchorDeserialize {
+    /// The discriminator the data of accounts of this type starts with, if
+    /// it has one. `#[account]` types do.
+    fn discriminator() -> Option<[u8; 8]> {
+        None
+    }
+
     /// Deserializes previously initialized account data. Should fail for all
     /// uninitialized accounts, where the bytes are zeroed. Implementations
     /// should be unique to a particular account type so that one can never
@@ -199,6 +240,11 @@ pub trait AccountDeserialize: crate::prelude::AnchorDeserialize {
     /// it should be impossible to deserialize a `Mint` account into a token
     /// `Account`.
     fn try_deserialize(buf: &mut &[u8]) -> otter_solana_program::Result<Self> {
+        if let Some(discriminator) = Self::discriminator() {
+            if !buf.starts_with(&discriminator) {
+                return Err(crate::prelude::Error::AccountDiscriminatorMismatch);
+            }
+        }
         Self::try_deserialize_unchecked(buf)
     }
 
@@ -206,10 +252,22 @@ pub trait AccountDeserialize: crate::prelude::AnchorDeserialize {
     /// This should only be used on account initialization, when the bytes of
     /// the account are zeroed.
     fn try_deserialize_unchecked(buf: &mut &[u8]) -> otter_solana_program::Result<Self> {
+        if Self::discriminator().is_some() {
+            if buf.len() < 8 {
+                return Err(crate::prelude::Error::AccountDiscriminatorNotFound);
+            }
+            *buf = &buf[8..];
+        }
         Self::deserialize(buf).map_err(|_| crate::prelude::Error::AccountDidNotDeserialize)
     }
 }
 
+/// The 8 bytes account data starts with, unique to each account type: the
+/// first bytes of `sha256("account:<Name>")`.
+pub trait Discriminator {
+    const DISCRIMINATOR: [u8; 8];
+}
+
 pub trait Accounts<'info>: ToAccountMetas + ToAccountInfos<'info> + Sized {
     /// Returns the validated accounts struct. What constitutes "valid" is
     /// program dependent. However, users of these types should never have to
@@ -257,6 +315,11 @@ pub trait Id {
     fn id() -> Pubkey;
 }
 
+/// The ids of the programs implementing an interface.
+pub trait Ids {
+    fn ids() -> Vec<Pubkey>;
+}
+
 pub trait Space {
     const INIT_SPACE: usize;
 }
@@ -4,7 +4,6 @@ use crate::{prelude::Result, ToAccountInfo};
 use otter_solana_program::{account_info::AccountInfo, pubkey::Pubkey};
 
 #[derive(Clone)]
-#[cfg_attr(any(kani, feature = "kani"), derive(kani::Arbitrary))]
 pub struct Program<'info, T> {
     info: AccountInfo<'info>,
     _phantom: PhantomData<T>,
@@ -43,6 +42,16 @@ impl<'a, T> Program<'a, T> {
     }
 }
 
+/// Like anchor, only accounts of the program `T` are accepted.
+#[cfg(any(kani, feature = "kani"))]
+impl<'info, T: crate::Id> kani::Arbitrary for Program<'info, T> {
+    fn any() -> Self {
+        let info: AccountInfo<'info> = kani::any();
+        kani::assume(*info.key == T::id());
+        Self::new(info)
+    }
+}
+
 impl<'info, T> Default for Program<'info, T>
 where
     T: Default,
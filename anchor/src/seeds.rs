@@ -0,0 +1,223 @@
+//! Seed schemas of the PDAs of a program, as written in `seeds` constraints,
+//! and the checks that two of them can't derive the same address.
+
+/// The longest concatenation of seeds a schema can have in the symbolic check.
+pub const MAX_SCHEMA_LEN: usize = 128;
+
+/// A seed of a `seeds` constraint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Seed {
+    /// A seed with the same bytes every time, e.g. `b"vault"`.
+    Const(&'static [u8]),
+    /// A seed whose bytes depend on the accounts or arguments, e.g. a key.
+    Var { min_len: usize, max_len: usize },
+}
+
+impl Seed {
+    /// The bytes the seed matches, `None` standing for any byte, and whether
+    /// each of them may be left out.
+    fn pattern(&self) -> Vec<(Option<u8>, bool)> {
+        match *self {
+            Seed::Const(bytes) => bytes.iter().map(|b| (Some(*b), false)).collect(),
+            Seed::Var { min_len, max_len } => (0..max_len).map(|i| (None, i >= min_len)).collect(),
+        }
+    }
+}
+
+/// The length of the bytes `seed` returns, e.g. 8 for `amount.to_le_bytes()`
+/// of a `u64`. The closure is never called, it only lets the compiler infer
+/// the length from the accounts and instruction arguments `A` it reads.
+pub const fn bytes_len<A, F, const N: usize>(_seed: &F) -> usize
+where
+    F: Fn(A) -> [u8; N],
+{
+    N
+}
+
+/// The seeds of a PDA account field of an accounts struct.
+#[derive(Clone, Copy, Debug)]
+pub struct SeedSchema {
+    pub accounts: &'static str,
+    pub field: &'static str,
+    /// The account type of the field, empty if it isn't an `Account`.
+    pub ty: &'static str,
+    /// Whether the PDA is derived from another program with `seeds::program`.
+    pub custom_program: bool,
+    pub seeds: &'static [Seed],
+}
+
+impl SeedSchema {
+    /// Whether the schema should be checked against `other`. The same seeds
+    /// for the same account type are the same role, used by several
+    /// instructions, and PDAs of other programs don't collide with ours.
+    fn comparable(&self, other: &SeedSchema) -> bool {
+        !self.custom_program
+            && !other.custom_program
+            && (self.ty != other.ty || self.seeds != other.seeds)
+    }
+
+    fn pattern(&self) -> Vec<(Option<u8>, bool)> {
+        self.seeds.iter().flat_map(Seed::pattern).collect()
+    }
+
+    /// Whether some bytes of the variable seeds of both schemas make their
+    /// concatenations equal.
+    pub fn may_collide(&self, other: &SeedSchema) -> bool {
+        let a = self.pattern();
+        let b = other.pattern();
+
+        // Positions reached in both patterns after matching the same bytes.
+        let mut reached = vec![vec![false; b.len() + 1]; a.len() + 1];
+        let mut todo = vec![(0, 0)];
+        while let Some((i, j)) = todo.pop() {
+            if reached[i][j] {
+                continue;
+            }
+            reached[i][j] = true;
+
+            if i < a.len() && a[i].1 {
+                todo.push((i + 1, j));
+            }
+            if j < b.len() && b[j].1 {
+                todo.push((i, j + 1));
+            }
+            if i < a.len() && j < b.len() {
+                let same = match (a[i].0, b[j].0) {
+                    (Some(x), Some(y)) => x == y,
+                    _ => true,
+                };
+                if same {
+                    todo.push((i + 1, j + 1));
+                }
+            }
+        }
+        reached[a.len()][b.len()]
+    }
+
+    /// Arbitrary bytes matching the schema, padded with zeroes, and their
+    /// length.
+    #[cfg(any(kani, feature = "kani"))]
+    fn any_bytes(&self) -> ([u8; MAX_SCHEMA_LEN], usize) {
+        let mut bytes = [0; MAX_SCHEMA_LEN];
+        let mut len = 0;
+        for seed in self.seeds {
+            match *seed {
+                Seed::Const(c) => {
+                    for b in c {
+                        bytes[len] = *b;
+                        len += 1;
+                    }
+                }
+                Seed::Var { min_len, max_len } => {
+                    let seed_len: usize = kani::any();
+                    kani::assume(min_len <= seed_len && seed_len <= max_len);
+                    for i in 0..max_len {
+                        if i < seed_len {
+                            bytes[len] = kani::any();
+                            len += 1;
+                        }
+                    }
+                }
+            }
+        }
+        (bytes, len)
+    }
+
+    #[cfg(any(kani, feature = "kani"))]
+    fn max_len(&self) -> usize {
+        self.seeds
+            .iter()
+            .map(|seed| match *seed {
+                Seed::Const(c) => c.len(),
+                Seed::Var { max_len, .. } => max_len,
+            })
+            .sum()
+    }
+}
+
+/// Every pair of schemas of different roles, among the schemas of all the
+/// accounts structs of a program.
+fn pairs<'a>(schemas: &'a [&'a [SeedSchema]]) -> Vec<(&'a SeedSchema, &'a SeedSchema)> {
+    let all = schemas.iter().flat_map(|s| s.iter()).collect::<Vec<_>>();
+    let mut res = vec![];
+    for (i, a) in all.iter().enumerate() {
+        for b in all[i + 1..].iter() {
+            if a.comparable(b) {
+                res.push((*a, *b));
+            }
+        }
+    }
+    res
+}
+
+/// Panics listing the schemas that may collide.
+pub fn assert_no_collisions(schemas: &[&[SeedSchema]]) {
+    let collisions = pairs(schemas)
+        .into_iter()
+        .filter(|(a, b)| a.may_collide(b))
+        .map(|(a, b)| {
+            format!(
+                "{}::{} and {}::{}",
+                a.accounts, a.field, b.accounts, b.field
+            )
+        })
+        .collect::<Vec<_>>();
+    assert!(
+        collisions.is_empty(),
+        "PDA seed collisions: {}",
+        collisions.join(", ")
+    );
+}
+
+/// Checks symbolically that no two schemas derive the same address.
+#[cfg(any(kani, feature = "kani"))]
+pub fn verify_no_collisions(schemas: &[&[SeedSchema]]) {
+    for (a, b) in pairs(schemas) {
+        kani::assert(
+            a.max_len() <= MAX_SCHEMA_LEN && b.max_len() <= MAX_SCHEMA_LEN,
+            "Seeds too long for the collision check.",
+        );
+        let (a_bytes, a_len) = a.any_bytes();
+        let (b_bytes, b_len) = b.any_bytes();
+        kani::assert(
+            a_len != b_len || a_bytes != b_bytes,
+            "PDA seed collision: two seed schemas can derive the same address",
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(field: &'static str, seeds: &'static [Seed]) -> SeedSchema {
+        SeedSchema {
+            accounts: "Accounts",
+            field,
+            ty: field,
+            custom_program: false,
+            seeds,
+        }
+    }
+
+    #[test]
+    fn test_may_collide() {
+        const NAME: Seed = Seed::Var {
+            min_len: 0,
+            max_len: 32,
+        };
+        const KEY: Seed = Seed::Var {
+            min_len: 32,
+            max_len: 32,
+        };
+
+        let user = schema("user", &[Seed::Const(b"user"), NAME]);
+        let user_ = schema("user_", &[Seed::Const(b"user_"), NAME]);
+        let vault = schema("vault", &[Seed::Const(b"vault"), KEY]);
+        let pool = schema("pool", &[Seed::Const(b"pool"), KEY]);
+
+        assert!(user.may_collide(&user_));
+        assert!(!vault.may_collide(&pool));
+        assert!(!user.may_collide(&vault));
+    }
+}
@@ -1,6 +1,6 @@
 use std::ops::Deref;
 
-use crate::prelude::ToAccountInfo;
+use crate::prelude::{SetAccountInfo, ToAccountInfo};
 use otter_solana_program::{account_info::AccountInfo, pubkey::Pubkey, Key};
 
 #[derive(Clone, Debug)]
@@ -21,6 +21,13 @@ impl<'info> ToAccountInfo<'info> for Signer<'info> {
     }
 }
 
+impl<'info> SetAccountInfo<'info> for Signer<'info> {
+    fn set_account_info(&mut self, info: AccountInfo<'info>) {
+        self.info = info;
+        self.key = info.key;
+    }
+}
+
 impl<'info> Deref for Signer<'info> {
     type Target = AccountInfo<'info>;
 
@@ -3,8 +3,11 @@ use crate::{Id, ToAccountInfos, ToAccountMetas};
 use crate::context::CpiContext;
 use crate::prelude::Result;
 use otter_solana_program::account_info::AccountInfo;
-use otter_solana_program::instruction::AccountMeta;
+use otter_solana_program::instruction::{AccountMeta, Instruction};
+use otter_solana_program::program::ProgramResult;
 use otter_solana_program::pubkey::Pubkey;
+use otter_solana_program::system_instruction;
+use otter_solana_program::system_program::{self, process_instruction};
 
 #[derive(Clone, Default)]
 #[cfg_attr(any(kani, feature = "kani"), derive(kani::Arbitrary))]
@@ -12,10 +15,21 @@ pub struct System;
 
 impl Id for System {
     fn id() -> Pubkey {
-        Pubkey::default()
+        system_program::ID
     }
 }
 
+/// Runs a System instruction of a helper on its accounts.
+fn process(instruction: &Instruction, account_infos: &[AccountInfo]) -> ProgramResult {
+    process_instruction(instruction, account_infos)?;
+    Ok(())
+}
+
+/// The accounts of an instruction built by a `system_instruction` builder.
+fn metas(instruction: Instruction) -> Vec<AccountMeta> {
+    instruction.accounts.to_vec()
+}
+
 #[derive(Debug)]
 #[cfg_attr(any(kani, feature = "kani"), derive(kani::Arbitrary))]
 pub struct Transfer<'info> {
@@ -24,14 +38,9 @@ pub struct Transfer<'info> {
 }
 
 impl ToAccountMetas for Transfer<'_> {
-    fn to_account_metas(
-        &self,
-        is_signer: Option<bool>,
-    ) -> Vec<otter_solana_program::instruction::AccountMeta> {
-        let is_signer = is_signer.unwrap_or(self.from.is_signer);
-        let from = self.from.to_account_meta(is_signer);
-        let to = self.to.to_account_meta(false);
-        vec![from, to]
+    fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let ix = system_instruction::transfer(self.from.key, self.to.key, 0);
+        metas(ix)
     }
 }
 
@@ -42,10 +51,11 @@ impl<'info> ToAccountInfos<'info> for Transfer<'info> {
 }
 
 pub fn transfer<'info>(
-    _ctx: CpiContext<'_, '_, '_, 'info, Transfer<'info>>,
-    _lamports: u64,
+    ctx: CpiContext<'_, '_, '_, 'info, Transfer<'info>>,
+    lamports: u64,
 ) -> Result<()> {
-    Ok(())
+    let ix = system_instruction::transfer(ctx.accounts.from.key, ctx.accounts.to.key, lamports);
+    ctx.invoke(ix, &[System::id()], process)
 }
 
 #[derive(Debug)]
@@ -56,14 +66,10 @@ pub struct CreateAccount<'info> {
 }
 
 impl ToAccountMetas for CreateAccount<'_> {
-    fn to_account_metas(
-        &self,
-        is_signer: Option<bool>,
-    ) -> Vec<otter_solana_program::instruction::AccountMeta> {
-        let is_signer = is_signer.unwrap_or(self.from.is_signer);
-        let from = self.from.to_account_meta(is_signer);
-        let to = self.to.to_account_meta(false);
-        vec![from, to]
+    fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let owner = Pubkey::default();
+        let ix = system_instruction::create_account(self.from.key, self.to.key, 0, 0, &owner);
+        metas(ix)
     }
 }
 
@@ -74,12 +80,19 @@ impl<'info> ToAccountInfos<'info> for CreateAccount<'info> {
 }
 
 pub fn create_account<'info>(
-    _ctx: CpiContext<'_, '_, '_, 'info, CreateAccount<'info>>,
-    _lamports: u64,
-    _space: u64,
-    _program: &Pubkey,
+    ctx: CpiContext<'_, '_, '_, 'info, CreateAccount<'info>>,
+    lamports: u64,
+    space: u64,
+    owner: &Pubkey,
 ) -> Result<()> {
-    Ok(())
+    let ix = system_instruction::create_account(
+        ctx.accounts.from.key,
+        ctx.accounts.to.key,
+        lamports,
+        space,
+        owner,
+    );
+    ctx.invoke(ix, &[System::id()], process)
 }
 
 #[derive(Debug)]
@@ -89,10 +102,9 @@ pub struct Allocate<'info> {
 }
 
 impl<'info> ToAccountMetas for Allocate<'info> {
-    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
-        let is_signer = is_signer.unwrap_or(self.account_to_allocate.is_signer);
-        let meta = self.account_to_allocate.to_account_meta(is_signer);
-        vec![meta]
+    fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let ix = system_instruction::allocate(self.account_to_allocate.key, 0);
+        metas(ix)
     }
 }
 
@@ -103,10 +115,11 @@ impl<'info> ToAccountInfos<'info> for Allocate<'info> {
 }
 
 pub fn allocate<'info>(
-    _ctx: CpiContext<'_, '_, '_, 'info, Allocate<'info>>,
-    _space: u64,
+    ctx: CpiContext<'_, '_, '_, 'info, Allocate<'info>>,
+    space: u64,
 ) -> Result<()> {
-    Ok(())
+    let ix = system_instruction::allocate(ctx.accounts.account_to_allocate.key, space);
+    ctx.invoke(ix, &[System::id()], process)
 }
 
 #[derive(Debug)]
@@ -116,10 +129,9 @@ pub struct Assign<'info> {
 }
 
 impl<'info> ToAccountMetas for Assign<'info> {
-    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
-        let is_signer = is_signer.unwrap_or(self.account_to_assign.is_signer);
-        let meta = self.account_to_assign.to_account_meta(is_signer);
-        vec![meta]
+    fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let ix = system_instruction::assign(self.account_to_assign.key, &Pubkey::default());
+        metas(ix)
     }
 }
 
@@ -130,8 +142,27 @@ impl<'info> ToAccountInfos<'info> for Assign<'info> {
 }
 
 pub fn assign<'info>(
-    _ctx: CpiContext<'_, '_, '_, 'info, Assign<'info>>,
-    _program_id: &Pubkey,
+    ctx: CpiContext<'_, '_, '_, 'info, Assign<'info>>,
+    owner: &Pubkey,
 ) -> Result<()> {
-    Ok(())
+    let ix = system_instruction::assign(ctx.accounts.account_to_assign.key, owner);
+    ctx.invoke(ix, &[System::id()], process)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer() {
+        let (from_key, to_key) = (Pubkey { t: [1] }, Pubkey { t: [2] });
+        let from = AccountInfo::new(&from_key, true, true, 10, 0, system_program::ID);
+        let to = AccountInfo::new(&to_key, false, true, 0, 0, system_program::ID);
+        let program = AccountInfo::new(&system_program::ID, false, false, 1, 0, Pubkey::default());
+
+        let ctx = CpiContext::new(program, Transfer { from, to });
+        assert!(transfer(ctx, 4).is_ok());
+        assert_eq!(from.lamports(), 6);
+        assert_eq!(to.lamports(), 4);
+    }
 }
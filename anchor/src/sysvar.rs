@@ -6,23 +6,56 @@ pub struct Sysvar<'info, T> {
     account: T,
 }
 
+impl<'info, T: otter_solana_program::sysvar::Sysvar> Sysvar<'info, T> {
+    /// Reads the sysvar from `info`, which has to be the sysvar account, as
+    /// anchor checks.
+    #[cfg(any(kani, feature = "kani"))]
+    pub fn from_account_info(
+        info: &AccountInfo<'info>,
+    ) -> otter_solana_program::Result<Sysvar<'info, T>>
+    where
+        T: kani::Arbitrary,
+    {
+        if !T::check_id(info.key) {
+            return Err(otter_solana_program::error::Error::AccountSysvarMismatch);
+        }
+        Ok(Self {
+            info: *info,
+            account: kani::any(),
+        })
+    }
+}
+
 impl<'info, T> ToAccountInfo<'info> for Sysvar<'info, T> {
     fn to_account_info(&self) -> AccountInfo<'info> {
         self.info.clone()
     }
 }
 
+impl<'info, T> std::ops::Deref for Sysvar<'info, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.account
+    }
+}
+
 impl<'info> Sysvar<'info, Rent> {
     pub fn minimum_balance(&self, data_len: usize) -> u64 {
         self.account.minimum_balance(data_len)
     }
 }
 
+/// Like anchor, only the sysvar account is accepted.
 #[cfg(any(kani, feature = "kani"))]
-impl<'info, T: kani::Arbitrary> kani::Arbitrary for Sysvar<'info, T> {
+impl<'info, T: kani::Arbitrary + otter_solana_program::sysvar::SysvarId> kani::Arbitrary
+    for Sysvar<'info, T>
+{
     fn any() -> Self {
+        let info: AccountInfo<'info> = kani::any();
+        kani::assume(T::check_id(info.key));
         Self {
-            info: kani::any(),
+            info,
             account: kani::any(),
         }
     }
@@ -0,0 +1,214 @@
+#![cfg(feature = "kani")]
+use std::fmt::Debug;
+
+use onchor::{
+    account::Account, prelude::*, program::Program, signer::Signer, system_program::System,
+};
+
+#[account]
+#[derive(Clone, Debug)]
+#[invariant(self.value == 1)]
+pub struct TestAccount {
+    pub value: u8,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8)]
+pub struct CreateTransaction<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"test",
+        ],
+        bump = 1,
+        constraint = test_account_mut.account.value == 1 && test_account_init.account.value == 1 && nonce == 1
+    )]
+    pub test_account_mut: Account<'info, TestAccount>,
+
+    #[account(
+        init,
+        seeds = [
+            b"test",
+        ],
+        bump,
+        space = 8 + mem::size_of::<TestAccount>(),
+        payer = creator
+    )]
+    pub test_account_init: Account<'info, TestAccount>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"test",
+        ],
+        bump = 1,
+        close = creator
+    )]
+    pub test_account_close: Account<'info, TestAccount>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[test]
+fn test_invariants() {
+    let mut tx = CreateTransaction::<'_> {
+        test_account_mut: Account::<'_, TestAccount> {
+            account: TestAccount { value: 1 },
+            info: Default::default(),
+        },
+        test_account_init: Account::<'_, TestAccount> {
+            account: TestAccount { value: 1 },
+            info: Default::default(),
+        },
+        test_account_close: Account::<'_, TestAccount> {
+            account: TestAccount { value: 1 },
+            info: Default::default(),
+        },
+        creator: Signer::<'_> {
+            info: Default::default(),
+            key: &Default::default(),
+        },
+        system_program: Default::default(),
+    };
+    assert!(tx.__pre_invariants());
+
+    fn initialize(tx: &mut CreateTransaction<'_>) {
+        tx.test_account_init.account.value = 1;
+    }
+
+    initialize(&mut tx);
+
+    assert!(tx.__post_invariants());
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, AnchorDeserialize, AnchorSerialize, kani::Arbitrary)]
+pub enum MarketState {
+    Open,
+    Paused,
+    Closed,
+}
+
+#[account]
+#[derive(Debug)]
+#[invariant()]
+#[transitions(status: Open -> Paused, Paused -> Open, Open -> Closed)]
+pub struct Market {
+    pub status: MarketState,
+}
+
+#[test]
+fn test_transitions() {
+    let open = Market {
+        status: MarketState::Open,
+    };
+    let closed = Market {
+        status: MarketState::Closed,
+    };
+
+    assert!(closed._check_transitions(&open));
+    assert!(!open._check_transitions(&closed));
+    assert!(open._check_transitions(&open.clone()));
+
+    assert!(closed._transition_allowed(&open, "status", &[("Open", "Closed")]));
+    assert!(!closed._transition_allowed(&open, "status", &[("Open", "Paused")]));
+}
+
+#[account]
+#[derive(Debug)]
+#[invariant()]
+pub struct Vault {
+    #[immutable]
+    pub authority: Pubkey,
+    #[write_once]
+    pub mint: Pubkey,
+    #[monotonic(increasing)]
+    pub nonce: u64,
+}
+
+#[test]
+fn test_temporal() {
+    let before = Vault {
+        authority: Pubkey::new_from_array([1]),
+        mint: Pubkey::default(),
+        nonce: 1,
+    };
+
+    let after = Vault {
+        mint: Pubkey::new_from_array([2]),
+        nonce: 2,
+        ..before.clone()
+    };
+    assert!(after._check_temporal(&before));
+
+    let rewritten = Vault {
+        mint: Pubkey::new_from_array([3]),
+        ..after.clone()
+    };
+    assert!(!rewritten._check_temporal(&after));
+
+    let decreased = Vault {
+        nonce: 0,
+        ..before.clone()
+    };
+    assert!(!decreased._check_temporal(&before));
+
+    let reassigned = Vault {
+        authority: Pubkey::new_from_array([2]),
+        ..before.clone()
+    };
+    assert!(!reassigned._check_temporal(&before));
+}
+
+#[derive(Accounts)]
+#[instruction(pairs: Vec<(u8, u8)>, nonce: u8)]
+pub struct SwapPairs<'info> {
+    #[account(constraint = pairs.len() == test_account.account.value as usize && nonce == 1)]
+    pub test_account: Account<'info, TestAccount>,
+}
+
+#[test]
+fn test_instruction_args() {
+    let tx = SwapPairs::<'_> {
+        test_account: Account::<'_, TestAccount> {
+            account: TestAccount { value: 1 },
+            info: Default::default(),
+        },
+    };
+    let mut pairs = Vec::new();
+    pairs.push((1, 2));
+    assert!(tx.__check_constraints(pairs, 1));
+    assert!(!tx.__check_constraints(pairs, 2));
+}
+
+#[account]
+#[derive(Debug)]
+#[invariant()]
+pub struct Pool {
+    pub bump: u8,
+}
+
+#[derive(Accounts)]
+pub struct CreatePools<'info> {
+    #[account(seeds = [b"first"], bump = first.account.bump)]
+    pub first: Account<'info, Pool>,
+    #[account(seeds = [b"second"], bump = second.account.bump)]
+    pub second: Account<'info, Pool>,
+}
+
+#[test]
+fn test_stored_bumps() {
+    let program_id = Pubkey::default();
+    let pool = |seed: &[u8]| Account::<'_, Pool> {
+        account: Pool {
+            bump: Pubkey::find_program_address(&[seed], &program_id).1,
+        },
+        info: Default::default(),
+    };
+    let tx = CreatePools::<'_> {
+        first: pool(b"first"),
+        second: pool(b"second"),
+    };
+    assert!(tx.__stored_bumps_canonical(&program_id));
+}
@@ -0,0 +1,65 @@
+use onchor::prelude::invariant;
+
+#[invariant(self.num == 1)]
+struct InvariantTest {
+    num: u64,
+}
+
+#[invariant()]
+struct EmptyInvariantTest;
+
+#[test]
+fn test_invariant_true() {
+    let t = InvariantTest { num: 1 };
+    assert!(t._check_invariant());
+}
+
+#[test]
+fn test_invariant_false() {
+    let t = InvariantTest { num: 2 };
+    assert!(!t._check_invariant());
+}
+
+#[test]
+fn test_empty_always_true() {
+    let t = EmptyInvariantTest;
+    assert!(t._check_invariant());
+}
+
+#[invariant(self.total >= 1)]
+struct NestedInvariantTest {
+    total: u64,
+    inner: InvariantTest,
+    items: Vec<InvariantTest>,
+    #[skip_invariant]
+    skipped: InvariantTest,
+}
+
+#[test]
+fn test_nested_invariant() {
+    let t = NestedInvariantTest {
+        total: 1,
+        inner: InvariantTest { num: 1 },
+        items: vec![InvariantTest { num: 1 }],
+        skipped: InvariantTest { num: 2 },
+    };
+    assert!(!t.skipped._check_invariant());
+    assert!(t._check_invariant());
+
+    let t = NestedInvariantTest {
+        inner: InvariantTest { num: 2 },
+        ..t
+    };
+    assert!(!t._check_invariant());
+}
+
+#[test]
+fn test_nested_vec_invariant() {
+    let t = NestedInvariantTest {
+        total: 1,
+        inner: InvariantTest { num: 1 },
+        items: vec![InvariantTest { num: 1 }, InvariantTest { num: 2 }],
+        skipped: InvariantTest { num: 1 },
+    };
+    assert!(!t._check_invariant());
+}
@@ -1,2 +1,3 @@
+pub mod token;
 pub mod token_2022;
 pub mod token_interface;
@@ -1,8 +1,48 @@
 use onchor::{
-    prelude::{AccountInfo, AccountMeta},
+    prelude::{AccountInfo, AccountMeta, Pubkey},
+    solana_program::instruction::Instruction,
     ToAccountInfos, ToAccountMetas,
 };
 
+/// Reserved like the id of the token program, see `token::ID`.
+pub static ID: Pubkey = Pubkey { t: [47] };
+
+pub fn id() -> Pubkey {
+    ID
+}
+
+/// The tag of `TransferChecked` in the token instruction data.
+const TRANSFER_CHECKED: u8 = 12;
+
+/// Builds a `TransferChecked` instruction with a single authority, like
+/// `spl_token_2022::instruction::transfer_checked`: the authority signs, and
+/// only the source and destination are writable.
+pub fn transfer_checked(
+    token_program_id: &Pubkey,
+    source: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    let accounts = [
+        AccountMeta::new(*source, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(*authority, true),
+    ];
+    let mut data = [0; 10];
+    data[0] = TRANSFER_CHECKED;
+    data[1..9].copy_from_slice(&amount.to_le_bytes());
+    data[9] = decimals;
+    Instruction {
+        program_id: *token_program_id,
+        accounts: accounts.into(),
+        data: data.into(),
+    }
+}
+
 #[derive(Debug)]
 #[cfg_attr(any(kani, feature = "kani"), derive(kani::Arbitrary))]
 pub struct TransferChecked<'info> {
@@ -13,13 +53,17 @@ pub struct TransferChecked<'info> {
 }
 
 impl ToAccountMetas for TransferChecked<'_> {
-    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
-        let is_signer = is_signer.unwrap_or(self.from.is_signer);
-        let from = self.from.to_account_meta(is_signer);
-        let to = self.to.to_account_meta(false);
-        let mint = self.mint.to_account_meta(false);
-        let authority = self.authority.to_account_meta(false);
-        vec![from, to, mint, authority]
+    fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let ix = transfer_checked(
+            &ID,
+            self.from.key,
+            self.mint.key,
+            self.to.key,
+            self.authority.key,
+            0,
+            0,
+        );
+        ix.accounts.to_vec()
     }
 }
 
@@ -27,8 +71,8 @@ impl<'info> ToAccountInfos<'info> for TransferChecked<'info> {
     fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
         vec![
             self.from.clone(),
-            self.to.clone(),
             self.mint.clone(),
+            self.to.clone(),
             self.authority.clone(),
         ]
     }
@@ -1,6 +1,6 @@
 use onchor::prelude::*;
 
-use crate::token_2022::TransferChecked;
+use crate::token_2022::{self, TransferChecked};
 
 #[derive(Clone, Debug, Default, PartialEq, AnchorDeserialize, AnchorSerialize)]
 #[cfg_attr(any(kani, feature = "kani"), derive(kani::Arbitrary))]
@@ -26,10 +26,27 @@ impl AccountDeserialize for TokenInterface {}
 
 impl AccountSerialize for TokenInterface {}
 
+impl Ids for TokenInterface {
+    fn ids() -> std::vec::Vec<Pubkey> {
+        vec![crate::token::ID, crate::token_2022::ID]
+    }
+}
+
 pub fn transfer_checked<'info>(
-    _ctx: CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>,
-    _amount: u64,
-    _decimals: u8,
+    ctx: CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>,
+    amount: u64,
+    decimals: u8,
 ) -> Result<()> {
-    Ok(())
+    let ix = token_2022::transfer_checked(
+        ctx.program.key,
+        ctx.accounts.from.key,
+        ctx.accounts.mint.key,
+        ctx.accounts.to.key,
+        ctx.accounts.authority.key,
+        amount,
+        decimals,
+    );
+    // The token programs have no model, so the transfer leaves the accounts
+    // unchanged.
+    ctx.invoke(ix, &TokenInterface::ids(), |_, _| Ok(()))
 }
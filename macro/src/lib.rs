@@ -16,6 +16,13 @@ pub fn derive_init_space(item: TokenStream) -> TokenStream {
     core::space::derive_init_space(item.into()).unwrap().into()
 }
 
+#[proc_macro_derive(BoundedArbitrary, attributes(max_len, arbitrary))]
+pub fn derive_bounded_arbitrary(item: TokenStream) -> TokenStream {
+    core::arbitrary::derive_arbitrary(item.into())
+        .unwrap()
+        .into()
+}
+
 #[proc_macro_attribute]
 pub fn account(args: TokenStream, item: TokenStream) -> TokenStream {
     core::account::account(args.into(), item.into())
@@ -41,7 +48,7 @@ pub fn invariant(attr: TokenStream, item: TokenStream) -> TokenStream {
 #[proc_macro_attribute]
 pub fn program(args: TokenStream, item: TokenStream) -> TokenStream {
     core::program::program(args.into(), item.into())
-        .unwrap()
+        .unwrap_or_else(|e| e.to_compile_error())
         .into()
 }
 
@@ -58,9 +65,16 @@ pub fn access_control(args: TokenStream, item: TokenStream) -> TokenStream {
 }
 
 #[proc_macro_attribute]
-pub fn helper_fn(_args: TokenStream, item: TokenStream) -> TokenStream {
-    core::helper_fn::helper_fn(item.into())
-        .expect("helper_fn used on non-function?")
+pub fn helper_fn(args: TokenStream, item: TokenStream) -> TokenStream {
+    core::helper_fn::helper_fn(args.into(), item.into())
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+#[proc_macro_attribute]
+pub fn cpi_contract(args: TokenStream, item: TokenStream) -> TokenStream {
+    core::cpi_contract::cpi_contract(args.into(), item.into())
+        .unwrap_or_else(|e| e.to_compile_error())
         .into()
 }
 
@@ -2,7 +2,7 @@
 use {anyhow::Result, proc_macro2::TokenStream, quote::quote, syn::ItemFn};
 
 #[cfg(feature = "anchor")]
-use crate::program::remove_verify_ignore_statements;
+use crate::{loop_invariant::expand_loop_invariants, program::remove_verify_ignore_statements};
 
 #[cfg(feature = "anchor")]
 pub fn access_control(args: TokenStream, input: TokenStream) -> Result<TokenStream> {
@@ -21,6 +21,7 @@ pub fn access_control(args: TokenStream, input: TokenStream) -> Result<TokenStre
     };
 
     remove_verify_ignore_statements(&mut item_fn);
+    expand_loop_invariants(&mut item_fn);
 
     let fn_attrs = item_fn.attrs;
     let fn_vis = item_fn.vis;
@@ -1,9 +1,26 @@
 use anchor_syn::{AccountField, AccountsStruct, ConstraintGroup, Field, Ty};
 use anyhow::Result;
-use proc_macro2::{Group, Ident, Span, TokenStream};
-use quote::{quote, ToTokens};
-use syn::{ExprType, ItemStruct, LitStr};
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Attribute, ItemStruct, LitStr, Meta, NestedMeta, Token, Type, TypePath, Visibility,
+};
 
+use crate::{
+    security::{
+        create_alias_checks, create_bump_checks, create_cosplay_checks, create_owner_checks,
+        create_reinit_checks, create_seed_schemas, create_signer_checks,
+    },
+    temporal::{reinit_checks, take_temporal, temporal_checks},
+    transitions::{take_transitions, transition_table},
+};
+
+/// Keys are a single byte in the model, so the id is the first character of
+/// the base58 string. Bytes outside the base58 alphabet can't be produced,
+/// which reserves them for ids no program may share, like the sysvars' and
+/// the token programs'.
 pub fn declare_id(id_tokens: TokenStream) -> TokenStream {
     let account_id_str = syn::parse2::<LitStr>(id_tokens)
         .expect("declare_id should have a string argument")
@@ -26,7 +43,7 @@ fn is_field_boxed(field: &Field) -> bool {
     account_ty.boxed
 }
 
-fn get_valid_field(field: &AccountField) -> Option<&Field> {
+pub(crate) fn get_valid_field(field: &AccountField) -> Option<&Field> {
     let AccountField::Field(f) = &field else {
         return None;
     };
@@ -49,7 +66,7 @@ fn get_valid_ident_constraints(field: &AccountField) -> Option<(&Ident, &Constra
 fn create_constraints_checks(
     val: &AccountsStruct,
     arg_names: &[Ident],
-    arg_types: &[syn::Type],
+    arg_types: &[Type],
 ) -> TokenStream {
     let mut checks = vec![];
     let mut fields = vec![];
@@ -167,23 +184,207 @@ fn create_post_invariants(val: &AccountsStruct) -> TokenStream {
     }
 }
 
+fn get_valid_ident_account_type(field: &AccountField) -> Option<(&Ident, &TypePath)> {
+    let field = get_valid_field(field)?;
+    let Ty::Account(account_ty) = &field.ty else {
+        return None;
+    };
+    Some((&field.ident, &account_ty.account_type_path))
+}
+
+pub(crate) fn snapshot_ident(val: &AccountsStruct) -> Ident {
+    format_ident!("__{}Snapshot", val.ident)
+}
+
+/// Generates a copy of the account data of every `Account` field, which the
+/// harnesses take before running an instruction to compare against afterwards.
+fn create_snapshot(val: &AccountsStruct, vis: &Visibility) -> TokenStream {
+    let ident = &val.ident;
+    let generics = &val.generics;
+    let snapshot = snapshot_ident(val);
+    let (fields, types): (Vec<_>, Vec<_>) = val
+        .fields
+        .iter()
+        .filter_map(get_valid_ident_account_type)
+        .unzip();
+
+    quote! {
+        #[doc(hidden)]
+        #vis struct #snapshot {
+            #(pub #fields: #types),*
+        }
+
+        impl #generics #ident #generics {
+            pub fn __snapshot(&self) -> #snapshot {
+                #snapshot {
+                    #(#fields: self.#fields.account.clone()),*
+                }
+            }
+        }
+    }
+}
+
+/// The `Account` fields whose data the temporal annotations are checked on.
+/// Accounts created by the instruction held no data of their type before,
+/// and closed accounts are left zeroed, so they have no history to compare
+/// against and are skipped.
+fn temporal_fields(val: &AccountsStruct) -> Vec<&Ident> {
+    val.fields
+        .iter()
+        .filter(|field| {
+            get_valid_ident_constraints(field)
+                .map(|(_, c)| c.init.is_none() && !c.is_close())
+                .unwrap_or(false)
+        })
+        .filter_map(get_valid_ident_account_type)
+        .map(|(ident, _)| ident)
+        .collect()
+}
+
+/// Generates the checks comparing account data before and after an
+/// instruction: state transitions, checked on every `Account` field, and
+/// temporal field annotations, checked on the `temporal_fields`.
+fn create_history_checks(val: &AccountsStruct) -> TokenStream {
+    let ident = &val.ident;
+    let generics = &val.generics;
+    let snapshot = snapshot_ident(val);
+    let fields = val
+        .fields
+        .iter()
+        .filter_map(get_valid_ident_account_type)
+        .map(|(ident, _)| ident)
+        .collect::<Vec<_>>();
+    let temporal = temporal_fields(val);
+
+    if fields.is_empty() {
+        return quote! {
+            #[allow(unused_variables)]
+            impl #generics #ident #generics {
+                pub fn __check_transitions(&self, before: &#snapshot) -> bool {
+                    true
+                }
+
+                pub fn __check_handler_transitions(
+                    &self,
+                    before: &#snapshot,
+                    field: &str,
+                    allowed: &[(&str, &str)],
+                ) -> bool {
+                    true
+                }
+
+                pub fn __check_temporal(&self, before: &#snapshot) -> bool {
+                    true
+                }
+            }
+        };
+    }
+
+    // Account types without `#[transitions]` or temporal annotations have no
+    // inherent methods, so they resolve to this always-true fallback instead.
+    let fallback = quote! {
+        trait __NoHistory {
+            fn _check_transitions(&self, _before: &Self) -> bool {
+                true
+            }
+
+            fn _transition_allowed(
+                &self,
+                _before: &Self,
+                _field: &str,
+                _allowed: &[(&str, &str)],
+            ) -> bool {
+                true
+            }
+
+            fn _check_temporal(&self, _before: &Self) -> bool {
+                true
+            }
+        }
+        impl<T> __NoHistory for T {}
+    };
+
+    quote! {
+        #[allow(unused_variables)]
+        impl #generics #ident #generics {
+            pub fn __check_transitions(&self, before: &#snapshot) -> bool {
+                #fallback
+                #(self.#fields.account._check_transitions(&before.#fields))&&*
+            }
+
+            pub fn __check_handler_transitions(
+                &self,
+                before: &#snapshot,
+                field: &str,
+                allowed: &[(&str, &str)],
+            ) -> bool {
+                #fallback
+                #(self.#fields.account._transition_allowed(&before.#fields, field, allowed))&&*
+            }
+
+            pub fn __check_temporal(&self, before: &#snapshot) -> bool {
+                #fallback
+                true #(&& self.#temporal.account._check_temporal(&before.#temporal))*
+            }
+        }
+    }
+}
+
+/// Generates `__has_transition_table`, telling at compile time whether the
+/// type of an `Account` field has a transition table for a field of its data.
+fn create_transition_fields(val: &AccountsStruct) -> TokenStream {
+    let ident = &val.ident;
+    let generics = &val.generics;
+    let types = val
+        .fields
+        .iter()
+        .filter_map(get_valid_ident_account_type)
+        .map(|(_, ty)| ty)
+        .collect::<Vec<_>>();
+
+    quote! {
+        impl #generics #ident #generics {
+            pub const fn __has_transition_table(field: &str) -> bool {
+                // Account types without `#[transitions]` track no field.
+                trait __NoTransitionTable {
+                    const _TRANSITION_FIELDS: &'static [&'static str] = &[];
+                }
+                impl<T> __NoTransitionTable for T {}
+
+                account::__lists_contain(&[#(<#types>::_TRANSITION_FIELDS),*], field)
+            }
+        }
+    }
+}
+
+/// An argument of `#[instruction(...)]`, such as `amount: u64`.
+struct InstructionArg {
+    name: Ident,
+    ty: Type,
+}
+
+impl Parse for InstructionArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let ty = input.parse()?;
+        Ok(Self { name, ty })
+    }
+}
+
 pub fn derive_accounts(item: TokenStream) -> Result<TokenStream> {
     let arg_item = syn::parse2::<ItemStruct>(item.clone())?;
     let mut arg_names: Vec<Ident> = vec![];
-    let mut arg_types: Vec<syn::Type> = vec![];
+    let mut arg_types: Vec<Type> = vec![];
 
+    let vis = arg_item.vis.clone();
     for t in arg_item.attrs {
-        if t.path.to_token_stream().to_string() == "instruction" {
-            let g = syn::parse2::<Group>(t.tokens)?;
-            for arg in g.stream().to_string().split(',') {
-                let parsed_arg = syn::parse_str::<ExprType>(arg.trim())?;
-                arg_names.push(Ident::new(
-                    &parsed_arg.expr.to_token_stream().to_string(),
-                    Span::call_site(),
-                ));
-                arg_types.push(syn::parse_str::<syn::Type>(
-                    &parsed_arg.ty.to_token_stream().to_string(),
-                )?);
+        if t.path.is_ident("instruction") {
+            let args =
+                t.parse_args_with(Punctuated::<InstructionArg, Token![,]>::parse_terminated)?;
+            for arg in args {
+                arg_names.push(arg.name);
+                arg_types.push(arg.ty);
             }
         }
     }
@@ -217,9 +418,11 @@ pub fn derive_accounts(item: TokenStream) -> Result<TokenStream> {
     let arbitrary_impl = quote! {
         impl #generics kani::Arbitrary for #ident #generics {
             fn any() -> Self {
-                Self {
+                let mut accounts = Self {
                     #(#fields),*
-                }
+                };
+                accounts.__share_aliased_state();
+                accounts
             }
         }
     };
@@ -227,27 +430,149 @@ pub fn derive_accounts(item: TokenStream) -> Result<TokenStream> {
     let pre_invariant_impl = create_pre_invariants(&val);
     let post_invariant_impl = create_post_invariants(&val);
     let constraint_checks = create_constraints_checks(&val, &arg_names, &arg_types);
+    let snapshot = create_snapshot(&val, &vis);
+    let history_checks = create_history_checks(&val);
+    let transition_fields = create_transition_fields(&val);
+    let signer_checks = create_signer_checks(&val);
+    let owner_checks = create_owner_checks(&val);
+    let bump_checks = create_bump_checks(&val, &arg_names, &arg_types);
+    let seed_schemas = create_seed_schemas(&val, &arg_names, &arg_types);
+    let alias_checks = create_alias_checks(&val);
+    let cosplay_checks = create_cosplay_checks(&val);
+    let reinit_checks = create_reinit_checks(&val);
 
     let res = quote! {
         #arbitrary_impl
         #pre_invariant_impl
         #post_invariant_impl
         #constraint_checks
+        #snapshot
+        #history_checks
+        #transition_fields
+        #signer_checks
+        #owner_checks
+        #bump_checks
+        #seed_schemas
+        #alias_checks
+        #cosplay_checks
+        #reinit_checks
     };
 
     Ok(res)
 }
 
+fn derives(attrs: &[Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| {
+        let Ok(Meta::List(list)) = attr.parse_meta() else {
+            return false;
+        };
+        list.path.is_ident("derive")
+            && list.nested.iter().any(|n| match n {
+                NestedMeta::Meta(m) => m.path().is_ident(name),
+                NestedMeta::Lit(_) => false,
+            })
+    })
+}
+
+/// The discriminator of the account type `ident`, as anchor computes it.
+fn discriminator(ident: &Ident) -> [u8; 8] {
+    let preimage = format!("account:{ident}");
+    let mut discriminator = [0; 8];
+    discriminator.copy_from_slice(&anchor_syn::hash::hash(preimage.as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+/// The tag standing for the discriminator of `ident` in Kani builds. It only
+/// has to tell account types apart, so a cheap hash of the name will do.
+fn abstract_tag(ident: &Ident) -> [u8; 8] {
+    let tag = format!("account:{ident}")
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+    tag.to_le_bytes()
+}
+
 pub fn account(_args: TokenStream, input: TokenStream) -> Result<TokenStream> {
-    let item = syn::parse2::<ItemStruct>(input.clone())?;
-    let ident = item.ident;
+    let mut item = syn::parse2::<ItemStruct>(input)?;
+    let transitions = take_transitions(&mut item.attrs)?;
+    let transition_table = transition_table(&item, &transitions)?;
+    let temporal = take_temporal(&mut item)?;
+    let temporal_checks = temporal_checks(&item, &temporal);
+    let reinit_checks = reinit_checks(&item, &temporal);
+    let ident = &item.ident;
+
+    // Snapshots of account data taken by the harnesses rely on `Clone`, which
+    // anchor derives for every account type.
+    let clone = if derives(&item.attrs, "Clone") {
+        quote! {}
+    } else {
+        quote! { #[derive(Clone)] }
+    };
+
+    let discriminator = discriminator(ident);
+    let tag = abstract_tag(ident);
 
     let res = quote! {
-        #[derive(Arbitrary, AnchorDeserialize, AnchorSerialize)]
-        #input
+        #[derive(BoundedArbitrary, AnchorDeserialize, AnchorSerialize)]
+        #clone
+        #item
+
+        #[cfg(not(any(kani, feature = "kani")))]
+        impl Discriminator for #ident {
+            const DISCRIMINATOR: [u8; 8] = [#(#discriminator),*];
+        }
 
-        impl AccountSerialize for #ident {}
-        impl AccountDeserialize for #ident {}
+        #[cfg(any(kani, feature = "kani"))]
+        impl Discriminator for #ident {
+            const DISCRIMINATOR: [u8; 8] = [#(#tag),*];
+        }
+
+        impl AccountSerialize for #ident {
+            fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
+                std::io::Write::write_all(writer, &Self::DISCRIMINATOR)
+                    .map_err(|_| Error::AccountDidNotSerialize)?;
+                self.serialize(writer)
+                    .map_err(|_| Error::AccountDidNotSerialize)
+            }
+        }
+
+        impl AccountDeserialize for #ident {
+            fn discriminator() -> Option<[u8; 8]> {
+                Some(Self::DISCRIMINATOR)
+            }
+        }
+
+        #transition_table
+        #temporal_checks
+        #reinit_checks
     };
     Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temporal_fields() {
+        let val: AccountsStruct = syn::parse_quote! {
+            pub struct Open<'info> {
+                #[account(init, payer = payer, space = 100)]
+                pub created: Account<'info, Vault>,
+                #[account(mut, close = payer)]
+                pub closed: Account<'info, Vault>,
+                #[account(mut)]
+                pub kept: Account<'info, Vault>,
+                #[account(mut)]
+                pub payer: Signer<'info>,
+                pub system_program: Program<'info, System>,
+            }
+        };
+        let fields = temporal_fields(&val)
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(fields, ["kept"]);
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::Result;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_quote,
+    punctuated::Punctuated,
+    Data, DeriveInput, Expr, Field, Fields, GenericArgument, Ident, Path, PathArguments, Token,
+    Type,
+};
+
+/// The contents of `#[arbitrary(with = generator)]`.
+struct With(Path);
+
+impl Parse for With {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let kw: Ident = input.parse()?;
+        if kw != "with" {
+            return Err(syn::Error::new_spanned(kw, "expected `with = ...`"));
+        }
+        input.parse::<Token![=]>()?;
+        Ok(With(input.parse()?))
+    }
+}
+
+/// The name of the outermost type of `ty` and its first generic argument, e.g.
+/// `Vec` and `u8` for `Vec<u8>`.
+fn outer_type(ty: &Type) -> Option<(&Ident, Option<&Type>)> {
+    let Type::Path(tp) = ty else {
+        return None;
+    };
+    let segment = tp.path.segments.last()?;
+    let arg = match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().find_map(|a| match a {
+            GenericArgument::Type(t) => Some(t),
+            _ => None,
+        }),
+        _ => None,
+    };
+    Some((&segment.ident, arg))
+}
+
+/// Generates a value of type `ty`, where `max_len` bounds the length of the
+/// outermost collections, the first one applying to the outermost.
+fn generate(ty: &Type, max_len: &[Expr]) -> TokenStream {
+    let Some((first, rest)) = max_len.split_first() else {
+        return quote! { kani::any() };
+    };
+
+    match outer_type(ty) {
+        Some((ident, Some(_))) if ident == "Vec" && rest.is_empty() => {
+            quote! { Vec::any_bounded(#first) }
+        }
+        Some((ident, Some(inner))) if ident == "Vec" => {
+            let element = generate(inner, rest);
+            quote! { Vec::any_bounded_with(#first, || #element) }
+        }
+        Some((ident, _)) if ident == "String" => quote! { String::any_bounded(#first) },
+        Some((ident, Some(inner))) if ident == "Option" => {
+            let value = generate(inner, max_len);
+            quote! {
+                if kani::any() {
+                    Some(#value)
+                } else {
+                    None
+                }
+            }
+        }
+        _ => quote! { kani::any() },
+    }
+}
+
+fn generate_field(field: &Field) -> syn::Result<TokenStream> {
+    let mut max_len = vec![];
+    for attr in field.attrs.iter() {
+        if attr.path.is_ident("arbitrary") {
+            let With(generator) = attr.parse_args()?;
+            return Ok(quote! { #generator() });
+        } else if attr.path.is_ident("max_len") {
+            max_len = attr
+                .parse_args_with(Punctuated::<Expr, Token![,]>::parse_terminated)?
+                .into_iter()
+                .collect();
+        }
+    }
+    Ok(generate(&field.ty, &max_len))
+}
+
+fn generate_fields(fields: &Fields) -> syn::Result<TokenStream> {
+    match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|f| &f.ident);
+            let values = named
+                .named
+                .iter()
+                .map(generate_field)
+                .collect::<syn::Result<Vec<_>>>()?;
+            Ok(quote! { { #(#names: #values),* } })
+        }
+        Fields::Unnamed(unnamed) => {
+            let values = unnamed
+                .unnamed
+                .iter()
+                .map(generate_field)
+                .collect::<syn::Result<Vec<_>>>()?;
+            Ok(quote! { ( #(#values),* ) })
+        }
+        Fields::Unit => Ok(quote! {}),
+    }
+}
+
+/// Derives `kani::Arbitrary`, generating fast `Vec`, `String` and `Option`
+/// fields bounded by `#[max_len(...)]`, and fields with an
+/// `#[arbitrary(with = generator)]` attribute by calling `generator()`.
+pub fn derive_arbitrary(item: TokenStream) -> Result<TokenStream> {
+    let mut input = syn::parse2::<DeriveInput>(item)?;
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(kani::Arbitrary));
+    }
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let fields = generate_fields(&data.fields)?;
+            quote! { Self #fields }
+        }
+        Data::Enum(data) => {
+            let variants = data.variants.iter().collect::<Vec<_>>();
+            let Some((last, variants)) = variants.split_last() else {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "can't derive Arbitrary for an enum without variants",
+                )
+                .into());
+            };
+            let arms = variants
+                .iter()
+                .enumerate()
+                .map(|(i, variant)| {
+                    let ident = &variant.ident;
+                    let fields = generate_fields(&variant.fields)?;
+                    Ok(quote! { #i => Self::#ident #fields, })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            let last_ident = &last.ident;
+            let last_fields = generate_fields(&last.fields)?;
+            quote! {
+                match kani::any::<usize>() {
+                    #(#arms)*
+                    _ => Self::#last_ident #last_fields,
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(
+                syn::Error::new_spanned(&input.ident, "can't derive Arbitrary for a union").into(),
+            )
+        }
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics kani::Arbitrary for #ident #ty_generics #where_clause {
+            fn any() -> Self {
+                #body
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_arbitrary() {
+        let input = quote! {
+            pub struct Params {
+                #[max_len(4, 8)]
+                pub names: Vec<String>,
+                #[max_len(2)]
+                pub memo: Option<Vec<u8>>,
+                #[arbitrary(with = small_fee)]
+                pub fee: u16,
+                pub amount: u64,
+            }
+        };
+
+        let expected = quote! {
+            impl kani::Arbitrary for Params {
+                fn any() -> Self {
+                    Self {
+                        names: Vec::any_bounded_with(4, || String::any_bounded(8)),
+                        memo: if kani::any() {
+                            Some(Vec::any_bounded(2))
+                        } else {
+                            None
+                        },
+                        fee: small_fee(),
+                        amount: kani::any()
+                    }
+                }
+            }
+        };
+
+        let res = derive_arbitrary(input).unwrap();
+        assert_eq!(res.to_string(), expected.to_string());
+    }
+}
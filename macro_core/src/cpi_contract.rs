@@ -0,0 +1,135 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse::{Parse, ParseStream, Parser},
+    punctuated::Punctuated,
+    Expr, FnArg, Ident, ItemFn, LitStr, Pat, Token, Type,
+};
+
+/// A `requires = ...` or `ensures = ...` clause of a CPI contract.
+struct Clause {
+    kind: Ident,
+    expr: Expr,
+}
+
+impl Parse for Clause {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let kind: Ident = input.parse()?;
+        if kind != "requires" && kind != "ensures" {
+            return Err(syn::Error::new_spanned(
+                kind,
+                "expected `requires = ...` or `ensures = ...`",
+            ));
+        }
+        input.parse::<Token![=]>()?;
+        let expr = input.parse()?;
+        Ok(Clause { kind, expr })
+    }
+}
+
+/// Whether `ty` is an `AccountInfo`, taken by value or by reference.
+fn is_account_info(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(r) => matches!(r.elem.as_ref(), Type::Path(_)) && is_account_info(&r.elem),
+        Type::Path(tp) => tp
+            .path
+            .segments
+            .last()
+            .is_some_and(|s| s.ident == "AccountInfo"),
+        _ => false,
+    }
+}
+
+/// Replaces the body of a wrapper around a CPI with its contract when
+/// verifying: the preconditions are asserted at the call site, the lamports,
+/// data and discriminator of the writable `AccountInfo` arguments are
+/// havocked, and the postconditions are assumed of them. `before_<arg>` holds
+/// an account as it was before the call. Like `invoke*`, the wrapper checks
+/// the changes the caller made to the accounts before the call, and the
+/// havocked state is not held against the caller.
+pub fn cpi_contract(args: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
+    let item = syn::parse2::<ItemFn>(input)
+        .map_err(|e| syn::Error::new(e.span(), "use #[cpi_contract] on a function"))?;
+
+    let clauses = Punctuated::<Clause, Token![,]>::parse_terminated.parse2(args)?;
+    let requires = clauses
+        .iter()
+        .filter(|c| c.kind == "requires")
+        .map(|c| &c.expr)
+        .collect::<Vec<_>>();
+    let ensures = clauses
+        .iter()
+        .filter(|c| c.kind == "ensures")
+        .map(|c| &c.expr)
+        .collect::<Vec<_>>();
+
+    let mut accounts = vec![];
+    let mut snapshots = vec![];
+    let mut havocs = vec![];
+    for input in item.sig.inputs.iter() {
+        let FnArg::Typed(a) = input else {
+            continue;
+        };
+        let Pat::Ident(pi) = a.pat.as_ref() else {
+            continue;
+        };
+        if !is_account_info(&a.ty) {
+            continue;
+        }
+        let ident = &pi.ident;
+        let before = format_ident!("before_{}", ident);
+        accounts.push(quote! { #ident.clone() });
+        snapshots.push(quote! { let #before = #ident.detached(); });
+        havocs.push(quote! {
+            if #ident.is_writable {
+                **#ident.lamports.borrow_mut() = kani::any();
+                #ident.realloc(kani::any(), false).unwrap();
+                #ident.set_discriminator(kani::any());
+            }
+        });
+    }
+
+    let function_name = &item.sig.ident;
+    let message = LitStr::new(
+        &format!("CPI contract precondition of `{function_name}` may not hold"),
+        function_name.span(),
+    );
+    let attrs = &item.attrs;
+    let vis = &item.vis;
+    let sig = &item.sig;
+    Ok(quote! {
+        #[cfg(not(kani))]
+        #item
+
+        #[cfg(kani)]
+        #(#attrs)*
+        #[allow(unused_variables)]
+        #vis #sig {
+            kani::assert(true #(&& (#requires))*, #message);
+            let __accounts = [#(#accounts),*];
+            anchor_lang::solana_program::runtime::sync_accounts(
+                &__accounts,
+                anchor_lang::solana_program::program::current_program_id().as_ref(),
+            );
+            #(#snapshots)*
+            #(#havocs)*
+            anchor_lang::solana_program::runtime::sync_accounts(&__accounts, None);
+            kani::assume(true #(&& (#ensures))*);
+            Ok(Default::default())
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_account_info() {
+        let ty = |s: &str| syn::parse_str::<Type>(s).unwrap();
+        assert!(is_account_info(&ty("AccountInfo<'info>")));
+        assert!(is_account_info(&ty("&AccountInfo<'info>")));
+        assert!(!is_account_info(&ty("&&AccountInfo<'info>")));
+        assert!(!is_account_info(&ty("u64")));
+    }
+}
@@ -1,17 +1,145 @@
 #[cfg(feature = "anchor")]
-use {anyhow::Result, proc_macro2::TokenStream, quote::quote, syn::ItemFn};
+use {
+    proc_macro2::TokenStream,
+    quote::{format_ident, quote},
+    syn::{
+        parenthesized,
+        parse::{Parse, ParseStream, Parser},
+        punctuated::Punctuated,
+        Attribute, Expr, FnArg, Ident, ItemFn, Pat, Token, Type,
+    },
+};
 
 #[cfg(feature = "anchor")]
-use crate::program::remove_verify_ignore_statements;
+use crate::{
+    loop_invariant::expand_loop_invariants,
+    program::{remove_verify_ignore_statements, take_unwind, KANI_UNWIND_AMOUNT},
+};
 
+/// A `requires(...)` or `ensures(...)` clause of a helper function contract.
 #[cfg(feature = "anchor")]
-pub fn helper_fn(input: TokenStream) -> Result<TokenStream> {
-    let Ok(mut item) = syn::parse2::<ItemFn>(input) else {
-        panic!("use #[helper_fn] on a function")
-    };
+enum Clause {
+    Requires(Expr),
+    Ensures(Expr),
+}
+
+#[cfg(feature = "anchor")]
+impl Parse for Clause {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let kind: Ident = input.parse()?;
+        let content;
+        parenthesized!(content in input);
+        let expr = content.parse()?;
+        if kind == "requires" {
+            Ok(Clause::Requires(expr))
+        } else if kind == "ensures" {
+            Ok(Clause::Ensures(expr))
+        } else {
+            Err(syn::Error::new_spanned(
+                kind,
+                "expected `requires(...)` or `ensures(...)`",
+            ))
+        }
+    }
+}
+
+/// Whether `attr` is a `#[helper_fn(...)]` with a contract.
+#[cfg(feature = "anchor")]
+pub fn has_contract(attr: &Attribute) -> bool {
+    attr.path.is_ident("helper_fn") && !attr.tokens.is_empty()
+}
+
+/// Generates the harness proving that `item` satisfies its contract for all
+/// arguments allowed by its preconditions, unrolling loops `unwind` times.
+#[cfg(feature = "anchor")]
+fn contract_harness(item: &ItemFn, unwind: usize) -> syn::Result<TokenStream> {
+    if item.sig.receiver().is_some() || !item.sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item.sig,
+            "helper_fn contracts are only supported on non-generic free functions",
+        ));
+    }
+
+    let mut decls = vec![];
+    let mut args = vec![];
+    for input in item.sig.inputs.iter() {
+        let FnArg::Typed(a) = input else {
+            return Err(syn::Error::new_spanned(
+                input,
+                "helper_fn contracts are only supported on free functions",
+            ));
+        };
+        let Pat::Ident(pi) = a.pat.as_ref() else {
+            return Err(syn::Error::new_spanned(&a.pat, "Expected identifier"));
+        };
+        let ident = &pi.ident;
+
+        // References are passed as references to symbolic values.
+        match a.ty.as_ref() {
+            Type::Reference(r) if r.mutability.is_some() => {
+                let ty = &r.elem;
+                decls.push(quote! { let mut #ident: #ty = kani::any(); });
+                args.push(quote! { &mut #ident });
+            }
+            Type::Reference(r) => {
+                let ty = &r.elem;
+                decls.push(quote! { let #ident: #ty = kani::any(); });
+                args.push(quote! { &#ident });
+            }
+            ty => {
+                decls.push(quote! { let #ident: #ty = kani::any(); });
+                args.push(quote! { #ident });
+            }
+        }
+    }
+
+    let function_name = &item.sig.ident;
+    let proof_name = format_ident!("contract_{}", function_name, span = function_name.span());
+    Ok(quote! {
+        #[cfg(kani)]
+        #[kani::proof_for_contract(#function_name)]
+        #[kani::unwind(#unwind)]
+        pub fn #proof_name() {
+            #(#decls)*
+            #function_name(#(#args),*);
+        }
+    })
+}
+
+#[cfg(feature = "anchor")]
+pub fn helper_fn(args: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
+    let mut item = syn::parse2::<ItemFn>(input)
+        .map_err(|e| syn::Error::new(e.span(), "use #[helper_fn] on a function"))?;
     remove_verify_ignore_statements(&mut item);
+    expand_loop_invariants(&mut item);
+    let unwind = take_unwind(&mut item.attrs)?.unwrap_or(KANI_UNWIND_AMOUNT);
+
+    let clauses = Punctuated::<Clause, Token![,]>::parse_terminated.parse2(args)?;
+    if clauses.is_empty() {
+        return Ok(quote! {
+            #item
+        });
+    }
+
+    // `ensures` clauses refer to the return value as `result`.
+    for clause in clauses.iter() {
+        let attr = match clause {
+            Clause::Requires(expr) => quote! {
+                #[cfg_attr(kani, kani::requires(#expr))]
+            },
+            Clause::Ensures(expr @ Expr::Closure(_)) => quote! {
+                #[cfg_attr(kani, kani::ensures(#expr))]
+            },
+            Clause::Ensures(expr) => quote! {
+                #[cfg_attr(kani, kani::ensures(|result| #expr))]
+            },
+        };
+        item.attrs.extend(Attribute::parse_outer.parse2(attr)?);
+    }
 
+    let harness = contract_harness(&item, unwind)?;
     Ok(quote! {
         #item
+        #harness
     })
 }
@@ -1,31 +1,144 @@
 use anyhow::Result;
 use proc_macro2::TokenStream;
-use quote::quote;
-use syn::{Expr, ItemStruct};
+use quote::{quote, ToTokens};
+use syn::{Expr, Fields, GenericArgument, ItemStruct, PathArguments, Type};
+
+/// Field attribute that opts a field out of invariant composition.
+const SKIP_INVARIANT: &str = "skip_invariant";
+
+/// The wrapper and the type it holds, for `Box<T>`, `Option<T>` and `Vec<T>`.
+fn wrapped(ty: &Type) -> Option<(String, &Type)> {
+    let Type::Path(tp) = ty else {
+        return None;
+    };
+    let segment = tp.path.segments.last()?;
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    let Some(GenericArgument::Type(inner)) = args.args.first() else {
+        return None;
+    };
+    let wrapper = segment.ident.to_string();
+    ["Box", "Option", "Vec"]
+        .contains(&wrapper.as_str())
+        .then_some((wrapper, inner))
+}
+
+/// The invariant check of the value of type `ty` that `value`, a reference,
+/// points to. Wrappers are looked through, as they would otherwise resolve to
+/// the always-true fallback: an empty option holds, and so does every element
+/// of a vector or array.
+fn field_check(value: TokenStream, ty: &Type) -> TokenStream {
+    if let Type::Array(array) = ty {
+        let check = field_check(quote! { x }, &array.elem);
+        return quote! { #value.iter().all(|x| #check) };
+    }
+    match wrapped(ty) {
+        Some((wrapper, inner)) if wrapper == "Box" => field_check(quote! { (&**#value) }, inner),
+        Some((wrapper, inner)) if wrapper == "Option" => {
+            let check = field_check(quote! { x }, inner);
+            quote! { #value.as_ref().is_none_or(|x| #check) }
+        }
+        Some((_, inner)) => {
+            let check = field_check(quote! { x }, inner);
+            quote! { #value.iter().all(|x| #check) }
+        }
+        None => quote! { #value._check_invariant() },
+    }
+}
+
+/// Collects the invariant checks of every field type that has one.
+///
+/// Whether a field type has an invariant is only known to the compiler, so the
+/// generated code calls `_check_invariant` on every field and relies on the
+/// inherent method generated by `#[invariant]` taking precedence over the
+/// always-true fallback trait emitted alongside the checks.
+fn nested_invariants(item: &mut ItemStruct) -> Vec<TokenStream> {
+    let mut nested = vec![];
+    let fields = match &mut item.fields {
+        Fields::Named(f) => &mut f.named,
+        Fields::Unnamed(f) => &mut f.unnamed,
+        Fields::Unit => return nested,
+    };
+
+    for (i, field) in fields.iter_mut().enumerate() {
+        let skip = field.attrs.iter().any(|a| a.path.is_ident(SKIP_INVARIANT));
+        field.attrs.retain(|a| !a.path.is_ident(SKIP_INVARIANT));
+        if skip {
+            continue;
+        }
+
+        let member = match &field.ident {
+            Some(ident) => ident.to_token_stream(),
+            None => syn::Index::from(i).to_token_stream(),
+        };
+
+        nested.push(field_check(quote! { (&self.#member) }, &field.ty));
+    }
+    nested
+}
 
 pub fn invariant(attr: TokenStream, item: TokenStream) -> Result<TokenStream> {
-    let item = syn::parse2::<ItemStruct>(item)?;
+    let mut item = syn::parse2::<ItemStruct>(item)?;
     #[allow(clippy::redundant_clone)]
     let ident = item.ident.clone();
-    let res = match syn::parse2::<Expr>(attr) {
-        Ok(attr) => quote! {
-            #item
+    let nested = nested_invariants(&mut item);
 
-            impl #ident {
-                pub fn _check_invariant(&self) -> bool {
-                    #attr
-                }
-            }
-        },
-        Err(_) => quote! {
-            #item
+    let own = match syn::parse2::<Expr>(attr) {
+        Ok(attr) => attr.to_token_stream(),
+        Err(_) => quote! { true },
+    };
 
-            impl #ident {
-                pub fn _check_invariant(&self) -> bool {
+    let body = if nested.is_empty() {
+        own
+    } else {
+        quote! {
+            // Types without an `#[invariant]` have no inherent
+            // `_check_invariant`, so they resolve to this one instead.
+            trait __NoInvariant {
+                fn _check_invariant(&self) -> bool {
                     true
                 }
             }
-        },
+            impl<T: ?Sized> __NoInvariant for T {}
+
+            (#own) #(&& #nested)*
+        }
+    };
+
+    let res = quote! {
+        #item
+
+        impl #ident {
+            pub fn _check_invariant(&self) -> bool {
+                #body
+            }
+        }
     };
     Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_check() {
+        let check = |ty: Type| field_check(quote! { (&self.fees) }, &ty).to_string();
+        assert_eq!(
+            check(syn::parse_quote!(Fees)),
+            quote! { (&self.fees)._check_invariant() }.to_string()
+        );
+        assert_eq!(
+            check(syn::parse_quote!(Box<Fees>)),
+            quote! { (&**(&self.fees))._check_invariant() }.to_string()
+        );
+        assert_eq!(
+            check(syn::parse_quote!(Vec<Option<Fees>>)),
+            quote! {
+                (&self.fees).iter().all(|x| x.as_ref().is_none_or(|x| x._check_invariant()))
+            }
+            .to_string()
+        );
+    }
+}
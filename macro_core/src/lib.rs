@@ -1,9 +1,15 @@
 pub mod access_control;
 pub mod account;
+pub mod arbitrary;
+pub mod cpi_contract;
 pub mod error;
 pub mod helper_fn;
 pub mod invariant;
+pub mod loop_invariant;
+pub mod security;
 pub mod space;
+pub mod temporal;
+pub mod transitions;
 
 #[cfg(feature = "verify")]
 pub mod verify;
@@ -0,0 +1,77 @@
+use syn::{
+    parse_quote,
+    visit_mut::{self, VisitMut},
+    Attribute, ExprForLoop, ExprLoop, ExprWhile, ItemFn,
+};
+
+struct LoopInvariants;
+
+impl LoopInvariants {
+    fn rewrite(attrs: &mut [Attribute]) {
+        for attr in attrs.iter_mut() {
+            if attr.path.is_ident("loop_invariant") {
+                let tokens = &attr.tokens;
+                *attr = parse_quote! {
+                    #[cfg_attr(any(kani, feature = "kani"), kani::loop_invariant #tokens)]
+                };
+            }
+        }
+    }
+}
+
+impl VisitMut for LoopInvariants {
+    fn visit_expr_for_loop_mut(&mut self, node: &mut ExprForLoop) {
+        Self::rewrite(&mut node.attrs);
+        visit_mut::visit_expr_for_loop_mut(self, node);
+    }
+
+    fn visit_expr_while_mut(&mut self, node: &mut ExprWhile) {
+        Self::rewrite(&mut node.attrs);
+        visit_mut::visit_expr_while_mut(self, node);
+    }
+
+    fn visit_expr_loop_mut(&mut self, node: &mut ExprLoop) {
+        Self::rewrite(&mut node.attrs);
+        visit_mut::visit_expr_loop_mut(self, node);
+    }
+}
+
+/// Turns every `#[loop_invariant(...)]` on a loop in `item` into a Kani loop
+/// contract, so the loop is verified inductively instead of being unrolled.
+/// Outside of Kani the attribute is dropped.
+pub fn expand_loop_invariants(item: &mut ItemFn) {
+    LoopInvariants.visit_item_fn_mut(item);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::quote;
+
+    #[test]
+    fn test_expand_loop_invariants() {
+        let mut item: ItemFn = parse_quote! {
+            fn sum(v: &Vec<u64>) -> u64 {
+                let mut i = 0;
+                #[loop_invariant(i <= v.len())]
+                while i < v.len() {
+                    i += 1;
+                }
+                i as u64
+            }
+        };
+        expand_loop_invariants(&mut item);
+
+        let expected = quote! {
+            fn sum(v: &Vec<u64>) -> u64 {
+                let mut i = 0;
+                #[cfg_attr(any(kani, feature = "kani"), kani::loop_invariant(i <= v.len()))]
+                while i < v.len() {
+                    i += 1;
+                }
+                i as u64
+            }
+        };
+        assert_eq!(quote! { #item }.to_string(), expected.to_string());
+    }
+}
@@ -1,17 +1,37 @@
-use anyhow::Result;
+use std::path::Path as FilePath;
+
+use anchor_syn::{parser::context::CrateContext, AccountsStruct};
 use proc_macro2::{Ident, Span, TokenStream};
-use quote::{format_ident, quote, ToTokens};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
 use syn::{
-    punctuated::Punctuated, token::Comma, Attribute, Expr, ExprArray, ExprAssign, ExprAssignOp,
-    ExprAsync, ExprAwait, ExprBinary, ExprBlock, ExprBox, ExprBreak, ExprCall, ExprCast,
-    ExprClosure, ExprContinue, ExprField, ExprForLoop, ExprGroup, ExprIf, ExprIndex, ExprLet,
-    ExprLit, ExprLoop, ExprMacro, ExprMatch, ExprMethodCall, ExprParen, ExprPath, ExprRange,
-    ExprReference, ExprRepeat, ExprReturn, ExprStruct, ExprTry, ExprTryBlock, ExprTuple, ExprType,
-    ExprUnary, ExprUnsafe, ExprWhile, ExprYield, FnArg, GenericArgument, Generics, Item, ItemFn,
-    ItemMod, Pat, PatType, PathArguments, Stmt, Type,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    token::Comma,
+    Attribute, Block, Expr, ExprArray, ExprAssign, ExprAssignOp, ExprAsync, ExprAwait, ExprBinary,
+    ExprBlock, ExprBox, ExprBreak, ExprCall, ExprCast, ExprClosure, ExprContinue, ExprField,
+    ExprForLoop, ExprGroup, ExprIf, ExprIndex, ExprLet, ExprLit, ExprLoop, ExprMacro, ExprMatch,
+    ExprMethodCall, ExprParen, ExprPath, ExprRange, ExprReference, ExprRepeat, ExprReturn,
+    ExprStruct, ExprTry, ExprTryBlock, ExprTuple, ExprType, ExprUnary, ExprUnsafe, ExprWhile,
+    ExprYield, FnArg, GenericArgument, Generics, Item, ItemFn, ItemMod, LitInt, LitStr, Member,
+    Pat, PatType, Path, PathArguments, Stmt, Token, Type,
+};
+
+use crate::{
+    helper_fn::has_contract,
+    loop_invariant::expand_loop_invariants,
+    security::{
+        account_field, bind_account_field, data_read_fields, derives_pdas, has_authorities,
+        owner_checked_fields, owner_unchecked_fields, reads_sysvars, unchecked_deserialize_fields,
+        uses_bumps,
+    },
+    transitions::TransitionSpec,
 };
 
-const KANI_UNWIND_AMOUNT: usize = 100;
+/// The unwind bound of the harnesses, unless set with `#[verify_unwind(n)]`.
+pub(crate) const KANI_UNWIND_AMOUNT: usize = 100;
+
+/// Enough to compare seeds of up to `anchor_lang::seeds::MAX_SCHEMA_LEN` bytes.
+const SEED_COLLISION_UNWIND: usize = 129;
 
 fn get_ctx_type(ctx_param: &PatType) -> syn::Result<Punctuated<GenericArgument, Comma>> {
     let Type::Path(pa) = ctx_param.ty.as_ref() else {
@@ -32,54 +52,159 @@ fn get_ctx_type(ctx_param: &PatType) -> syn::Result<Punctuated<GenericArgument,
     Err(syn::Error::new(Span::call_site(), "invalid context type"))
 }
 
+/// The arguments of the generated checks of an accounts struct, which take the
+/// `#[instruction(...)]` arguments.
+fn constraint_args(parameters: &[&PatType]) -> TokenStream {
+    let constraint_params = parameters
+        .iter()
+        .map(|p| Ident::new(&p.pat.to_token_stream().to_string(), Span::call_site()));
+    quote! { #(#constraint_params.clone()),* }
+}
+
 fn create_constraint_check(has_constraint: bool, parameters: &[&PatType]) -> TokenStream {
     if !has_constraint {
         quote! {
             let constraints = true;
         }
     } else {
-        let constraint_params = parameters
-            .iter()
-            .map(|p| Ident::new(&p.pat.to_token_stream().to_string(), Span::call_site()));
+        let args = constraint_args(parameters);
         quote! {
-            let constraints = ctx.accounts.__check_constraints(#(#constraint_params.clone()),*);
+            let constraints = ctx.accounts.__check_constraints(#args);
+        }
+    }
+}
+
+/// An instruction handler of a `#[program]` module that harnesses are
+/// generated for.
+struct Handler<'a> {
+    mod_name: &'a Ident,
+    function_name: &'a Ident,
+    generics: &'a Generics,
+    ctx_type: Punctuated<GenericArgument, Comma>,
+    parameters: Vec<&'a PatType>,
+    parameter_names: Vec<Ident>,
+    /// Extra attributes put on every harness, e.g. Kani stubs.
+    harness_attrs: Vec<TokenStream>,
+    /// Calls to the `#[verify_setup]` hooks, run on the fresh context.
+    setup: Vec<TokenStream>,
+    /// The `#[domain(...)]` conditions of the arguments.
+    domain: Vec<TokenStream>,
+    /// The unwind bound of the harnesses.
+    unwind: usize,
+}
+
+impl Handler<'_> {
+    /// Wraps `body` in a harness that starts the way all of them do: with
+    /// the symbolic arguments in their domain and context, the setup hooks
+    /// run on them, and the account invariants assumed. `body` sets `result`,
+    /// and the accounts left by a successful instruction are checked against
+    /// the runtime rules.
+    fn harness(&self, proof_name: &Ident, body: TokenStream) -> TokenStream {
+        self.harness_with(proof_name, self.domain_assumption(), body)
+    }
+
+    /// Same as `harness`, with `arguments` deciding what is assumed about the
+    /// symbolic arguments.
+    fn harness_with(
+        &self,
+        proof_name: &Ident,
+        arguments: TokenStream,
+        body: TokenStream,
+    ) -> TokenStream {
+        let generics = self.generics;
+        let ctx_type = &self.ctx_type;
+        let parameters = &self.parameters;
+        let harness_attrs = &self.harness_attrs;
+        let setup = &self.setup;
+        let unwind = self.unwind;
+        let conc = if setup.is_empty() {
+            quote! { conc }
+        } else {
+            quote! { mut conc }
+        };
+        quote! {
+            #[kani::proof]
+            #[kani::unwind(#unwind)]
+            #(#harness_attrs)*
+            pub fn #proof_name #generics () {
+                #(
+                    let #parameters = kani::any();
+                );*
+                #arguments
+
+                let #conc: anchor_lang::context::ConcreteContext<#ctx_type> = kani::any();
+                #(#setup)*
+                anchor_lang::solana_program::program::set_program_id(conc.program_id);
+                let ctx = conc.to_ctx();
+                kani::assume(conc.to_ctx().accounts.__pre_invariants());
+                let __runtime_before = conc.to_ctx().accounts.__account_infos().map(|info| {
+                    anchor_lang::solana_program::runtime::AccountSnapshot::of(&info)
+                });
+                anchor_lang::solana_program::runtime::begin_instruction(&__runtime_before);
+                #body
+                if result.is_ok() {
+                    let after = conc.to_ctx().accounts.__account_infos().map(|info| {
+                        anchor_lang::solana_program::runtime::AccountSnapshot::of(&info)
+                    });
+                    anchor_lang::solana_program::runtime::verify_runtime_rules(
+                        &__runtime_before,
+                        &after,
+                        &conc.program_id,
+                    );
+                }
+            }
+        }
+    }
+
+    fn domain_assumption(&self) -> TokenStream {
+        if self.domain.is_empty() {
+            quote! {}
+        } else {
+            let domain = self.domain();
+            quote! { kani::assume(#domain); }
+        }
+    }
+
+    /// Whether all arguments are in their domain.
+    fn domain(&self) -> TokenStream {
+        let domain = &self.domain;
+        quote! { #((#domain))&&* }
+    }
+
+    fn call(&self) -> TokenStream {
+        let mod_name = self.mod_name;
+        let function_name = self.function_name;
+        let parameter_names = &self.parameter_names;
+        quote! {
+            #mod_name::#function_name(#(#parameter_names),*)
         }
     }
 }
 
-#[allow(clippy::too_many_arguments)]
 fn create_succeeds_if(
-    mod_name: &Ident,
-    function_name: &Ident,
-    generics: &Generics,
-    ctx_type: &Punctuated<GenericArgument, Comma>,
+    handler: &Handler,
     attr: Attribute,
-    parameters: &[&PatType],
-    parameter_names: &[Ident],
     constraint_check: TokenStream,
+    transitions: &[TransitionSpec],
 ) -> syn::Result<TokenStream> {
     let precondition = match attr.parse_args::<Expr>() {
         Ok(p) => p.to_token_stream(),
         Err(_) => quote! { true },
     };
+    let function_name = handler.function_name;
     let proof_name = format_ident!("succeeds_if_{}", function_name, span = function_name.span());
+    let call = handler.call();
+    let history_checks = create_history_checks(transitions);
 
-    Ok(quote! {
-        #[kani::proof]
-        #[kani::unwind(#KANI_UNWIND_AMOUNT)]
-        pub fn #proof_name #generics () {
-            #(
-                let #parameters = kani::any();
-            );*
-
-            let conc: anchor_lang::context::ConcreteContext<#ctx_type> = kani::any();
-            let ctx = conc.to_ctx();
-            kani::assume(conc.to_ctx().accounts.__pre_invariants());
+    Ok(handler.harness(
+        &proof_name,
+        quote! {
             let precondition = #precondition;
             kani::assume(precondition);
+            let before = conc.accounts.__snapshot();
             #constraint_check
             let result = if constraints {
-                #mod_name::#function_name(#(#parameter_names),*)
+                #call
             } else {
                 err!("constraint check failed")
             };
@@ -87,43 +212,32 @@ fn create_succeeds_if(
                 result.is_ok(),
                 "function failed to succeed given a precondition"
             );
-        }
-    })
+            #history_checks
+        },
+    ))
 }
 
-#[allow(clippy::too_many_arguments)]
 fn create_errors_if(
-    mod_name: &Ident,
-    function_name: &Ident,
-    generics: &Generics,
-    ctx_type: &Punctuated<GenericArgument, Comma>,
+    handler: &Handler,
     attr: Attribute,
-    parameters: &[&PatType],
-    parameter_names: &[Ident],
     constraint_check: TokenStream,
 ) -> syn::Result<TokenStream> {
     let error_conds = match attr.parse_args::<Expr>() {
         Ok(p) => p.to_token_stream(),
         Err(_) => quote! { true },
     };
+    let function_name = handler.function_name;
     let proof_name = format_ident!("errors_if_{}", function_name, span = function_name.span());
+    let call = handler.call();
 
-    Ok(quote! {
-        #[kani::proof]
-        #[kani::unwind(#KANI_UNWIND_AMOUNT)]
-        pub fn #proof_name #generics () {
-            #(
-                let #parameters = kani::any();
-            );*
-
-            let conc: anchor_lang::context::ConcreteContext<#ctx_type> = kani::any();
-            let ctx = conc.to_ctx();
-            kani::assume(conc.to_ctx().accounts.__pre_invariants());
+    Ok(handler.harness(
+        &proof_name,
+        quote! {
             let error_conds = #error_conds;
             kani::assume(error_conds);
             #constraint_check
             let result = if constraints {
-                #mod_name::#function_name(#(#parameter_names),*)
+                #call
             } else {
                 err!("constraint check failed")
             };
@@ -131,37 +245,402 @@ fn create_errors_if(
                 result.is_err(),
                 "Function succeeded when it should have errored"
             );
-        }
-    })
+        },
+    ))
 }
 
-fn create_verify(
-    mod_name: &Ident,
-    function_name: &Ident,
-    generics: &Generics,
-    ctx_type: &Punctuated<GenericArgument, Comma>,
-    parameters: &[&PatType],
-    parameter_names: &[Ident],
-) -> syn::Result<TokenStream> {
-    let proof_name = format_ident!("verify_{}", function_name, span = function_name.span());
-    let res = quote! {
-        #[kani::proof]
-        #[kani::unwind(#KANI_UNWIND_AMOUNT)]
-        pub fn #proof_name #generics () {
+/// Generates the harness checking the handler rejects arguments outside of
+/// their domain, like a dispatcher validating them would, so that a domain the
+/// program doesn't enforce is flagged instead of silently assumed.
+fn create_domain_check(handler: &Handler, constraint_check: TokenStream) -> TokenStream {
+    let function_name = handler.function_name;
+    let proof_name = format_ident!("domain_{}", function_name, span = function_name.span());
+    let domain = handler.domain();
+    let call = handler.call();
+
+    handler.harness_with(
+        &proof_name,
+        quote! { kani::assume(!(#domain)); },
+        quote! {
+            #constraint_check
+            let result = if constraints {
+                #call
+            } else {
+                err!("constraint check failed")
+            };
+            kani::assert(
+                result.is_err(),
+                "Function accepted an argument outside of its domain"
+            );
+        },
+    )
+}
+
+/// Generates the harness checking the handler fails when one of the
+/// authorities of its accounts didn't sign the transaction, as anchor would
+/// with every account it requires to sign.
+fn create_missing_signer_check(handler: &Handler, constraint_check: TokenStream) -> TokenStream {
+    let function_name = handler.function_name;
+    let proof_name = format_ident!(
+        "missing_signer_{}",
+        function_name,
+        span = function_name.span()
+    );
+    let call = handler.call();
+
+    handler.harness(
+        &proof_name,
+        quote! {
+            let authorities = conc.accounts.__authority_keys();
+            let authority: usize = kani::any();
+            kani::assume(authority < authorities.len());
+            kani::assume(!conc.accounts.__signed(&authorities[authority], &conc.remaining_accounts));
+            #constraint_check
+            let result = if constraints && ctx.accounts.__check_signers() {
+                #call
+            } else {
+                err!("constraint check failed")
+            };
+            conc.to_ctx().accounts.__assert_signer_checked(authority, result.is_err());
+        },
+    )
+}
+
+/// Generates the harness checking the handler only succeeds when the raw
+/// accounts whose data it reads are owned by the program. Their owners are
+/// arbitrary, so the handler has to check them itself. `fields` are the ones
+/// whose owner it doesn't compare with anything.
+fn create_missing_owner_check(
+    handler: &Handler,
+    fields: &[Ident],
+    constraint_check: TokenStream,
+) -> TokenStream {
+    let function_name = handler.function_name;
+    let proof_name = format_ident!(
+        "missing_owner_{}",
+        function_name,
+        span = function_name.span()
+    );
+    let call = handler.call();
+    let names = fields.iter().map(|f| f.to_string());
+    let indices = 0..fields.len();
+    let messages = fields.iter().map(|f| {
+        LitStr::new(
+            &format!("Missing owner check: instruction trusted the data of `{f}`"),
+            f.span(),
+        )
+    });
+
+    handler.harness(
+        &proof_name,
+        quote! {
+            let owned = [#(conc.accounts.__owned_by(#names, &conc.program_id)),*];
+            #constraint_check
+            let result = if constraints && ctx.accounts.__check_signers() {
+                #call
+            } else {
+                err!("constraint check failed")
+            };
             #(
-                let #parameters = kani::any();
-            );*
+                kani::assert(result.is_err() || owned[#indices], #messages);
+            )*
+        },
+    )
+}
 
-            let conc: anchor_lang::context::ConcreteContext<#ctx_type> = kani::any();
-            let ctx = conc.to_ctx();
-            kani::assume(conc.to_ctx().accounts.__pre_invariants());
-            let result = #mod_name::#function_name(#(#parameter_names),*);
+/// Generates the harness checking the handler never succeeds after deriving
+/// an address from a bump that isn't canonical. With `#[has_constraint]`, the
+/// `seeds` constraints are checked too, bumps stored in accounts are assumed
+/// canonical before the instruction and checked to still be after it.
+fn create_bump_check(
+    handler: &Handler,
+    has_constraint: bool,
+    constraint_check: TokenStream,
+) -> TokenStream {
+    let function_name = handler.function_name;
+    let proof_name = format_ident!(
+        "non_canonical_bump_{}",
+        function_name,
+        span = function_name.span()
+    );
+    let call = handler.call();
+
+    let (seeds_check, stored_bumps_check) = if has_constraint {
+        let args = constraint_args(&handler.parameters);
+        (
+            quote! {
+                kani::assume(conc.accounts.__stored_bumps_canonical(&conc.program_id, #args));
+                let seeds = ctx.accounts.__check_seeds(ctx.program_id, #args);
+            },
+            quote! {
+                kani::assert(
+                    result.is_err()
+                        || conc.to_ctx().accounts.__stored_bumps_canonical(&conc.program_id, #args),
+                    "Non-canonical bump: instruction stored a bump that isn't canonical",
+                );
+            },
+        )
+    } else {
+        (quote! { let seeds = true; }, quote! {})
+    };
+
+    handler.harness(
+        &proof_name,
+        quote! {
+            #seeds_check
+            #constraint_check
+            let result = if constraints && seeds && ctx.accounts.__check_signers() {
+                #call
+            } else {
+                err!("constraint check failed")
+            };
+            kani::assert(
+                result.is_err() || !anchor_lang::solana_program::pubkey::non_canonical_bump_used(),
+                "Non-canonical bump: instruction succeeded with a bump that isn't canonical",
+            );
+            #stored_bumps_check
+        },
+    )
+}
+
+/// Generates the harness passing one account as two of the mutable accounts
+/// of the handler, which have to hold the same state if it succeeds. Anchor
+/// deserializes and writes back each field on its own, so the last one
+/// written wins, e.g. when a transfer's `from` and `to` are the same account.
+fn create_duplicate_mutable_check(handler: &Handler, constraint_check: TokenStream) -> TokenStream {
+    let function_name = handler.function_name;
+    let proof_name = format_ident!(
+        "duplicate_mutable_{}",
+        function_name,
+        span = function_name.span()
+    );
+    let call = handler.call();
+    let domain = handler.domain_assumption();
+
+    handler.harness_with(
+        &proof_name,
+        quote! {
+            #domain
+            anchor_lang::solana_program::pubkey::set_aliasing(true);
+        },
+        quote! {
+            let first: usize = kani::any();
+            let second: usize = kani::any();
+            kani::assume(conc.accounts.__mutable_aliases(first, second));
+            #constraint_check
+            let result = if constraints && ctx.accounts.__check_signers() {
+                #call
+            } else {
+                err!("constraint check failed")
+            };
+            if result.is_ok() {
+                conc.to_ctx().accounts.__assert_aliases_agree(first, second);
+            }
+        },
+    )
+}
+
+/// Generates the harness reporting instructions that succeed after
+/// deserializing the data of a raw account without checking its
+/// discriminator, so that data written as any type can be passed.
+fn create_unchecked_read_check(
+    handler: &Handler,
+    fields: &[Ident],
+    constraint_check: TokenStream,
+) -> TokenStream {
+    let function_name = handler.function_name;
+    let proof_name = format_ident!(
+        "unchecked_read_{}",
+        function_name,
+        span = function_name.span()
+    );
+    let call = handler.call();
+    let names = fields.iter().map(|f| f.to_string());
+    let indices = 0..fields.len();
+    let messages = fields.iter().map(|f| {
+        LitStr::new(
+            &format!(
+                "Type cosplay: instruction deserialized `{f}` without checking its discriminator"
+            ),
+            f.span(),
+        )
+    });
+
+    handler.harness(
+        &proof_name,
+        quote! {
+            let raw = [#(conc.accounts.__is_raw(#names)),*];
+            #constraint_check
+            let result = if constraints && ctx.accounts.__check_signers() {
+                #call
+            } else {
+                err!("constraint check failed")
+            };
+            #(
+                kani::assert(result.is_err() || !raw[#indices], #messages);
+            )*
+        },
+    )
+}
+
+/// The fields assigned to by the top level statements of `block`, as pairs
+/// of the accounts struct field holding the account and the field of its
+/// data, e.g. `("state", "authority")` for
+/// `state.authority = ctx.accounts.user.key();` after
+/// `let state = &mut ctx.accounts.state;`.
+fn unconditional_assignments(block: &Block) -> Vec<(String, String)> {
+    let mut bindings = vec![];
+    let mut res = vec![];
+    for stmt in block.stmts.iter() {
+        match stmt {
+            Stmt::Local(local) => bind_account_field(local, &mut bindings),
+            Stmt::Semi(Expr::Assign(assign), _) | Stmt::Expr(Expr::Assign(assign)) => {
+                let Expr::Field(field) = assign.left.as_ref() else {
+                    continue;
+                };
+                let (Member::Named(ident), Some(account)) =
+                    (&field.member, account_field(&field.base, &bindings))
+                else {
+                    continue;
+                };
+                res.push((account.to_string(), ident.to_string()));
+            }
+            _ => {}
+        }
+    }
+    res
+}
+
+/// Generates the harness checking the handler doesn't overwrite the
+/// authority or immutable fields of `init_if_needed` accounts that already
+/// existed, and doesn't assign to them unconditionally.
+fn create_reinit_check(
+    handler: &Handler,
+    block: &Block,
+    constraint_check: TokenStream,
+) -> TokenStream {
+    let function_name = handler.function_name;
+    let proof_name = format_ident!("reinit_{}", function_name, span = function_name.span());
+    let call = handler.call();
+    let (accounts, assigned): (Vec<_>, Vec<_>) =
+        unconditional_assignments(block).into_iter().unzip();
+
+    handler.harness(
+        &proof_name,
+        quote! {
+            conc.accounts.__assert_no_unconditional_reinit(&[#((#accounts, #assigned)),*]);
+            let before = conc.accounts.__snapshot();
+            let existed = conc.accounts.__existing_accounts(&conc.program_id);
+            #constraint_check
+            let result = if constraints && ctx.accounts.__check_signers() {
+                #call
+            } else {
+                err!("constraint check failed")
+            };
+            conc.to_ctx().accounts.__assert_not_reinitialized(&before, &existed, result.is_err());
+        },
+    )
+}
+
+/// Generates the harness reporting instructions that succeed after reading
+/// sysvar data from an account whose key wasn't checked to be the sysvar's,
+/// e.g. with `load_instruction_at`, so that a spoofed sysvar can be passed.
+fn create_sysvar_check(handler: &Handler, constraint_check: TokenStream) -> TokenStream {
+    let function_name = handler.function_name;
+    let proof_name = format_ident!(
+        "sysvar_spoofing_{}",
+        function_name,
+        span = function_name.span()
+    );
+    let call = handler.call();
+
+    handler.harness(
+        &proof_name,
+        quote! {
+            #constraint_check
+            let result = if constraints && ctx.accounts.__check_signers() {
+                #call
+            } else {
+                err!("constraint check failed")
+            };
+            kani::assert(
+                result.is_err() || !anchor_lang::solana_program::sysvar::unchecked_sysvar_read(),
+                "Sysvar spoofing: instruction read sysvar data from an account without checking its key",
+            );
+        },
+    )
+}
+
+/// Checks at compile time that an account of the instruction has a
+/// transition table for each field its `#[transitions]` names, since the
+/// harnesses couldn't tell its transitions apart otherwise.
+fn create_transition_table_checks(
+    accounts_type: &Type,
+    transitions: &[TransitionSpec],
+) -> TokenStream {
+    let mut accounts_type = accounts_type.clone();
+    if let Type::Path(tp) = &mut accounts_type {
+        if let Some(last) = tp.path.segments.last_mut() {
+            last.arguments = PathArguments::None;
+        }
+    }
+    let checks = transitions.iter().map(|spec| {
+        let field = spec.field.to_string();
+        let message = format!(
+            "#[transitions] names `{field}`, which no account of the instruction has a transition table for"
+        );
+        quote_spanned! {spec.field.span()=>
+            const _: () = assert!(#accounts_type::__has_transition_table(#field), #message);
+        }
+    });
+    quote! { #(#checks)* }
+}
+
+/// Checks the accounts left by a successful instruction against their
+/// temporal annotations and state transitions, comparing with `before`.
+fn create_history_checks(transitions: &[TransitionSpec]) -> TokenStream {
+    let checks = transitions.iter().map(|spec| {
+        let field = spec.field.to_string();
+        let allowed = spec.allowed();
+        quote! {
+            kani::assert(
+                result.is_err()
+                    || conc.to_ctx().accounts.__check_handler_transitions(&before, #field, #allowed),
+                "Function made a state transition it does not allow",
+            );
+        }
+    });
+
+    quote! {
+        kani::assert(
+            result.is_err() || conc.to_ctx().accounts.__check_temporal(&before),
+            "Function changed a field against its temporal annotation",
+        );
+        kani::assert(
+            result.is_err() || conc.to_ctx().accounts.__check_transitions(&before),
+            "Function made a state transition the program does not allow",
+        );
+        #(#checks)*
+    }
+}
+
+fn create_verify(handler: &Handler, transitions: &[TransitionSpec]) -> syn::Result<TokenStream> {
+    let function_name = handler.function_name;
+    let proof_name = format_ident!("verify_{}", function_name, span = function_name.span());
+    let call = handler.call();
+    let history_checks = create_history_checks(transitions);
+    let res = handler.harness(
+        &proof_name,
+        quote! {
+            let before = conc.accounts.__snapshot();
+            let result = #call;
             kani::assert(
                 result.is_err() || conc.to_ctx().accounts.__post_invariants(),
                 "Function failed",
             );
-        }
-    };
+            #history_checks
+        },
+    );
     Ok(res)
 }
 
@@ -226,8 +705,166 @@ pub fn remove_verify_ignore_statements(item: &mut ItemFn) {
     }
 }
 
-fn verification_harness_of(mod_name: &Ident, item: &mut ItemFn) -> syn::Result<TokenStream> {
+/// The contents of `#[verify_stub(original, replacement)]`.
+struct Stub {
+    original: Path,
+    replacement: Path,
+}
+
+impl Parse for Stub {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let original = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let replacement = input.parse()?;
+        Ok(Self {
+            original,
+            replacement,
+        })
+    }
+}
+
+/// Removes every `#[use_contracts(...)]` and `#[verify_stub(...)]` attribute
+/// from `attrs` and returns the Kani attributes they turn into on a harness.
+fn take_harness_attrs(attrs: &mut Vec<Attribute>) -> syn::Result<Vec<TokenStream>> {
+    let harness_attrs = take_attrs(attrs, |attr| {
+        if attr.path.is_ident("use_contracts") {
+            let paths = attr.parse_args_with(Punctuated::<Path, Comma>::parse_terminated)?;
+            Ok(Some(
+                paths
+                    .iter()
+                    .map(|path| quote! { #[kani::stub_verified(#path)] })
+                    .collect(),
+            ))
+        } else if attr.path.is_ident("verify_stub") {
+            let Stub {
+                original,
+                replacement,
+            } = attr.parse_args()?;
+            Ok(Some(vec![
+                quote! { #[kani::stub(#original, #replacement)] },
+            ]))
+        } else {
+            Ok(None)
+        }
+    })?;
+    Ok(harness_attrs.into_iter().flatten().collect())
+}
+
+/// Removes the attributes `parse` recognizes from `attrs` and returns what it
+/// parses them into. If one of them is malformed, `attrs` is left untouched.
+fn take_attrs<T>(
+    attrs: &mut Vec<Attribute>,
+    mut parse: impl FnMut(&Attribute) -> syn::Result<Option<T>>,
+) -> syn::Result<Vec<T>> {
+    let mut taken = vec![];
+    let mut kept = vec![];
+    for attr in attrs.iter() {
+        match parse(attr)? {
+            Some(t) => taken.push(t),
+            None => kept.push(attr.clone()),
+        }
+    }
+    *attrs = kept;
+    Ok(taken)
+}
+
+/// Removes every `#[verify_unwind(n)]` attribute from `attrs` and returns the
+/// unwind bound the last one sets.
+pub(crate) fn take_unwind(attrs: &mut Vec<Attribute>) -> syn::Result<Option<usize>> {
+    let unwind = take_attrs(attrs, |attr| {
+        if attr.path.is_ident("verify_unwind") {
+            Ok(Some(attr.parse_args::<LitInt>()?.base10_parse()?))
+        } else {
+            Ok(None)
+        }
+    })?;
+    Ok(unwind.last().copied())
+}
+
+/// Removes every `#[verify_setup(...)]` attribute from `attrs` and returns the
+/// hooks they name.
+fn take_setup_hooks(attrs: &mut Vec<Attribute>) -> syn::Result<Vec<Path>> {
+    let hooks = take_attrs(attrs, |attr| {
+        if attr.path.is_ident("verify_setup") {
+            let paths = attr.parse_args_with(Punctuated::<Path, Comma>::parse_terminated)?;
+            Ok(Some(paths.into_iter().collect::<Vec<_>>()))
+        } else {
+            Ok(None)
+        }
+    })?;
+    Ok(hooks.into_iter().flatten().collect())
+}
+
+/// Removes the `#[domain(...)]` attributes from the parameters of `item` and
+/// returns the conditions they put on the arguments. A range is a condition on
+/// the parameter it's placed on.
+fn take_domain(item: &mut ItemFn) -> syn::Result<Vec<TokenStream>> {
+    let mut domain = vec![];
+    let mut inputs = item.sig.inputs.clone();
+    for p in inputs.iter_mut() {
+        let FnArg::Typed(a) = p else { continue };
+        let pat = &a.pat;
+        domain.extend(take_attrs(&mut a.attrs, |attr| {
+            if !attr.path.is_ident("domain") {
+                return Ok(None);
+            }
+            let Pat::Ident(pi) = pat.as_ref() else {
+                return Err(syn::Error::new_spanned(pat, "Expected identifier"));
+            };
+            let name = &pi.ident;
+            match attr.parse_args::<Expr>()? {
+                Expr::Range(range) => Ok(Some(quote! { (#range).contains(&#name) })),
+                expr => Ok(Some(expr.to_token_stream())),
+            }
+        })?);
+    }
+    item.sig.inputs = inputs;
+    Ok(domain)
+}
+
+/// The harnesses of `item`, or `None` if it isn't an instruction handler, i.e.
+/// has no `ctx` parameter. If an attribute is malformed, `item` is left as it
+/// was and the error is returned.
+fn verification_harness_of(
+    mod_name: &Ident,
+    item: &mut ItemFn,
+    accounts: Option<&AccountsStruct>,
+    module_harness_attrs: &[TokenStream],
+    module_setup_hooks: &[Path],
+    module_unwind: usize,
+) -> syn::Result<Option<TokenStream>> {
+    let is_handler = item.sig.inputs.iter().any(|p| {
+        matches!(p, FnArg::Typed(a) if matches!(a.pat.as_ref(), Pat::Ident(pi) if pi.ident == "ctx"))
+    });
+    if !is_handler {
+        return Ok(None);
+    }
+
+    let original = item.clone();
+    let harness = handler_harnesses(
+        mod_name,
+        item,
+        accounts,
+        module_harness_attrs,
+        module_setup_hooks,
+        module_unwind,
+    );
+    if harness.is_err() {
+        *item = original;
+    }
+    harness.map(Some)
+}
+
+fn handler_harnesses(
+    mod_name: &Ident,
+    item: &mut ItemFn,
+    accounts: Option<&AccountsStruct>,
+    module_harness_attrs: &[TokenStream],
+    module_setup_hooks: &[Path],
+    module_unwind: usize,
+) -> syn::Result<TokenStream> {
     remove_verify_ignore_statements(item);
+    let domain = take_domain(item)?;
 
     let mut parameters = vec![];
     let mut parameter_names = vec![];
@@ -259,60 +896,171 @@ fn verification_harness_of(mod_name: &Ident, item: &mut ItemFn) -> syn::Result<T
         ));
     };
 
-    let function_name = &item.sig.ident;
-    let generics = &item.sig.generics;
     let mut succeeds_if_harness: Option<TokenStream> = None;
     let mut errors_if_harness: Option<TokenStream> = None;
     let mut create_succeeds_attr: Option<Attribute> = None;
     let mut create_errors_attr: Option<Attribute> = None;
     let mut has_constraint = false;
+    let mut transitions = vec![];
+    let mut harness_attrs = module_harness_attrs.to_vec();
+    harness_attrs.extend(take_harness_attrs(&mut item.attrs)?);
+    let unwind = take_unwind(&mut item.attrs)?.unwrap_or(module_unwind);
+
+    // Module hooks are shared by instructions with different contexts and
+    // arguments, so they are given the arguments as a tuple of references.
+    let arguments = parameters.iter().map(|p| &p.pat).collect::<Vec<_>>();
+    let mut setup = module_setup_hooks
+        .iter()
+        .map(|hook| quote! { #hook(&mut conc, (#(&#arguments,)*)); })
+        .collect::<Vec<_>>();
+    setup.extend(
+        take_setup_hooks(&mut item.attrs)?
+            .iter()
+            .map(|hook| quote! { #hook(&mut conc, #(&#arguments),*); }),
+    );
 
-    for attr in std::mem::take(&mut item.attrs).into_iter() {
+    let mut kept = vec![];
+    for attr in item.attrs.iter() {
         if attr.path.is_ident("succeeds_if") {
-            create_succeeds_attr = Some(attr);
+            create_succeeds_attr = Some(attr.clone());
         } else if attr.path.is_ident("errors_if") {
-            create_errors_attr = Some(attr);
+            create_errors_attr = Some(attr.clone());
         } else if attr.path.is_ident("has_constraint") {
             has_constraint = true;
+        } else if attr.path.is_ident("transitions") {
+            transitions.push(attr.parse_args::<TransitionSpec>()?);
         } else {
-            item.attrs.push(attr);
+            kept.push(attr.clone());
         }
     }
+    item.attrs = kept;
+
+    let handler = Handler {
+        mod_name,
+        function_name: &item.sig.ident,
+        generics: &item.sig.generics,
+        ctx_type,
+        parameters,
+        parameter_names,
+        harness_attrs,
+        setup,
+        domain,
+        unwind,
+    };
 
     if let Some(attr) = create_succeeds_attr {
         succeeds_if_harness = Some(create_succeeds_if(
-            mod_name,
-            function_name,
-            generics,
-            &ctx_type,
+            &handler,
             attr,
-            &parameters,
-            &parameter_names,
-            create_constraint_check(has_constraint, &parameters),
+            create_constraint_check(has_constraint, &handler.parameters),
+            &transitions,
         )?);
     }
 
     if let Some(attr) = create_errors_attr {
         errors_if_harness = Some(create_errors_if(
-            mod_name,
-            function_name,
-            generics,
-            &ctx_type,
+            &handler,
             attr,
-            &parameters,
-            &parameter_names,
-            create_constraint_check(has_constraint, &parameters),
+            create_constraint_check(has_constraint, &handler.parameters),
         )?);
     }
 
-    let verify = create_verify(
-        mod_name,
-        function_name,
-        generics,
-        &ctx_type,
-        &parameters,
-        &parameter_names,
-    )?;
+    let domain_harness = if handler.domain.is_empty() {
+        quote! {}
+    } else {
+        create_domain_check(
+            &handler,
+            create_constraint_check(has_constraint, &handler.parameters),
+        )
+    };
+
+    // Without its accounts struct, the harnesses are kept in case it has
+    // something for them to check.
+    let missing_signer = if accounts.is_none_or(has_authorities) {
+        create_missing_signer_check(
+            &handler,
+            create_constraint_check(has_constraint, &handler.parameters),
+        )
+    } else {
+        quote! {}
+    };
+
+    let non_canonical_bump =
+        if accounts.is_none_or(derives_pdas) || uses_bumps(item.block.to_token_stream()) {
+            create_bump_check(
+                &handler,
+                has_constraint,
+                create_constraint_check(has_constraint, &handler.parameters),
+            )
+        } else {
+            quote! {}
+        };
+
+    let duplicate_mutable = create_duplicate_mutable_check(
+        &handler,
+        create_constraint_check(has_constraint, &handler.parameters),
+    );
+
+    let reinit = create_reinit_check(
+        &handler,
+        &item.block,
+        create_constraint_check(has_constraint, &handler.parameters),
+    );
+
+    let owner_checked = owner_checked_fields(&item.block);
+    let data_read = data_read_fields(&item.block)
+        .into_iter()
+        .filter(|f| !owner_checked.contains(f))
+        .filter(|f| accounts.is_none_or(|a| owner_unchecked_fields(a).contains(&f)))
+        .collect::<Vec<_>>();
+    let missing_owner = if data_read.is_empty() {
+        quote! {}
+    } else {
+        create_missing_owner_check(
+            &handler,
+            &data_read,
+            create_constraint_check(has_constraint, &handler.parameters),
+        )
+    };
+
+    let unchecked = unchecked_deserialize_fields(&item.block);
+    let unchecked_read = if unchecked.is_empty() {
+        quote! {}
+    } else {
+        create_unchecked_read_check(
+            &handler,
+            &unchecked,
+            create_constraint_check(has_constraint, &handler.parameters),
+        )
+    };
+
+    let sysvar_spoofing = if reads_sysvars(item.block.to_token_stream()) {
+        create_sysvar_check(
+            &handler,
+            create_constraint_check(has_constraint, &handler.parameters),
+        )
+    } else {
+        quote! {}
+    };
+
+    let transition_table_checks = match accounts_type_of(item) {
+        Some(ty) if !transitions.is_empty() => create_transition_table_checks(&ty, &transitions),
+        _ => quote! {},
+    };
+
+    let verify = create_verify(&handler, &transitions)?;
+    let verify = quote! {
+        #verify
+        #transition_table_checks
+        #domain_harness
+        #missing_signer
+        #missing_owner
+        #non_canonical_bump
+        #duplicate_mutable
+        #unchecked_read
+        #reinit
+        #sysvar_spoofing
+    };
 
     let res = match (succeeds_if_harness, errors_if_harness) {
         (Some(succeeds_if_harness), Some(errors_if_harness)) => quote! {
@@ -333,21 +1081,147 @@ fn verification_harness_of(mod_name: &Ident, item: &mut ItemFn) -> syn::Result<T
     Ok(res)
 }
 
-pub fn program(_args: TokenStream, input: TokenStream) -> Result<TokenStream> {
+/// The accounts struct of the `ctx` parameter of an instruction.
+fn accounts_type_of(item: &ItemFn) -> Option<Type> {
+    item.sig.inputs.iter().find_map(|p| {
+        let FnArg::Typed(a) = p else { return None };
+        if a.pat.to_token_stream().to_string() != "ctx" {
+            return None;
+        }
+        get_ctx_type(a).ok()?.into_iter().find_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        })
+    })
+}
+
+/// The accounts structs of the crate, read from its sources as anchor reads
+/// them for the IDL, since `#[program]` only sees the handlers. Empty when
+/// the sources can't be parsed.
+fn crate_accounts_structs() -> Vec<AccountsStruct> {
+    let Ok(dir) = std::env::var("CARGO_MANIFEST_DIR") else {
+        return vec![];
+    };
+    let Ok(krate) = CrateContext::parse(FilePath::new(&dir).join("src").join("lib.rs")) else {
+        return vec![];
+    };
+    krate
+        .structs()
+        .filter(|s| s.attrs.iter().any(derives_accounts))
+        .filter_map(|s| anchor_syn::parser::accounts::parse(s).ok())
+        .collect()
+}
+
+fn derives_accounts(attr: &Attribute) -> bool {
+    attr.path.is_ident("derive")
+        && attr
+            .parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated)
+            .is_ok_and(|derives| {
+                derives
+                    .iter()
+                    .any(|d| d.segments.last().is_some_and(|s| s.ident == "Accounts"))
+            })
+}
+
+/// The accounts struct named like `ty`, unless none or several are.
+fn accounts_struct_of<'a>(structs: &'a [AccountsStruct], ty: &Type) -> Option<&'a AccountsStruct> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let name = &path.path.segments.last()?.ident;
+    let mut named = structs.iter().filter(|s| &s.ident == name);
+    match (named.next(), named.next()) {
+        (Some(accounts), None) => Some(accounts),
+        _ => None,
+    }
+}
+
+/// Generates the checks that the `seeds` of the accounts structs of the
+/// program can't derive the same address: a test comparing the seed schemas
+/// statically and `seed_collisions_<mod>`, which instantiates the ones of
+/// different roles symbolically.
+fn create_seed_collision_checks(mod_name: &Ident, accounts_types: &[Type]) -> TokenStream {
+    let test_name = format_ident!("seed_schemas_{}", mod_name, span = mod_name.span());
+    let proof_name = format_ident!("seed_collisions_{}", mod_name, span = mod_name.span());
+    quote! {
+        #[cfg(test)]
+        #[test]
+        fn #test_name() {
+            anchor_lang::seeds::assert_no_collisions(&[#(<#accounts_types>::__SEED_SCHEMAS),*]);
+        }
+
+        #[kani::proof]
+        #[kani::unwind(#SEED_COLLISION_UNWIND)]
+        pub fn #proof_name() {
+            anchor_lang::seeds::verify_no_collisions(&[#(<#accounts_types>::__SEED_SCHEMAS),*]);
+        }
+    }
+}
+
+/// Generates `type_cosplay_<mod>`, checking no `Account` field of the
+/// accounts structs of the program accepts data written as another type.
+fn create_type_cosplay_check(
+    mod_name: &Ident,
+    accounts_types: &[Type],
+    unwind: usize,
+) -> TokenStream {
+    let proof_name = format_ident!("type_cosplay_{}", mod_name, span = mod_name.span());
+    quote! {
+        #[kani::proof]
+        #[kani::unwind(#unwind)]
+        pub fn #proof_name() {
+            anchor_lang::account::verify_no_type_cosplay(&[#(<#accounts_types>::__account_types()),*]);
+        }
+    }
+}
+
+pub fn program(_args: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
     let mut item = syn::parse2::<ItemMod>(input)?;
+    let mut harness_attrs = take_harness_attrs(&mut item.attrs)?;
+    let setup_hooks = take_setup_hooks(&mut item.attrs)?;
+    let unwind = take_unwind(&mut item.attrs)?.unwrap_or(KANI_UNWIND_AMOUNT);
     let name = &item.ident;
     let items = &mut item.content;
     if items.is_none() {
-        panic!("#[program] must be placed on full modules:  {name}")
+        return Err(syn::Error::new_spanned(
+            name,
+            "#[program] must be placed on full modules",
+        ));
+    }
+
+    // Helpers with a contract in the module itself are used without listing them.
+    for item in &items.as_ref().unwrap().1 {
+        if let Item::Fn(item) = item {
+            if item.attrs.iter().any(has_contract) {
+                let ident = &item.sig.ident;
+                harness_attrs.push(quote! { #[kani::stub_verified(#name::#ident)] });
+            }
+        }
     }
 
     let mut harnesses = Vec::new();
+    let mut accounts_types = Vec::new();
+    for item in &mut items.as_mut().unwrap().1 {
+        if let Item::Fn(item) = item {
+            if let Some(ty) = accounts_type_of(item) {
+                if !accounts_types.contains(&ty) {
+                    accounts_types.push(ty);
+                }
+            }
+        }
+    }
+    harnesses.push(create_seed_collision_checks(name, &accounts_types));
+    let structs = crate_accounts_structs();
+    harnesses.push(create_type_cosplay_check(name, &accounts_types, unwind));
+
     for item in &mut items.as_mut().unwrap().1 {
         if let Item::Fn(item) = item {
-            if let Ok(harness) = verification_harness_of(name, item) {
+            expand_loop_invariants(item);
+            let accounts = accounts_type_of(item).and_then(|ty| accounts_struct_of(&structs, &ty));
+            if let Some(harness) =
+                verification_harness_of(name, item, accounts, &harness_attrs, &setup_hooks, unwind)?
+            {
                 harnesses.push(harness);
-            } else {
-                println!("ignored harness for: {:?}", item.sig.ident);
             }
         }
     }
@@ -0,0 +1,1202 @@
+use anchor_syn::{AccountField, AccountsStruct, Field, Ty};
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+use syn::{
+    punctuated::Punctuated,
+    visit_mut::{self, VisitMut},
+    BinOp, Block, Expr, ExprBinary, ExprCall, ExprField, ExprMethodCall, ExprPath, Lit, LitByteStr,
+    LitStr, Local, Macro, Member, Pat, RangeLimits, Token, Type,
+};
+
+use crate::account::{get_valid_field, snapshot_ident};
+
+/// Whether `field` holds a wallet rather than program owned data, i.e. an
+/// account that can act as an authority.
+fn is_wallet(field: &Field) -> bool {
+    matches!(
+        field.ty,
+        Ty::AccountInfo | Ty::UncheckedAccount | Ty::Signer | Ty::SystemAccount
+    )
+}
+
+/// Collects the identifiers `x` of every `x.key` and `x.key()` in `tokens`.
+fn key_checked_idents(tokens: TokenStream, res: &mut Vec<Ident>) {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    for (i, token) in tokens.iter().enumerate() {
+        match (token, tokens.get(i + 1), tokens.get(i + 2)) {
+            (TokenTree::Group(group), _, _) => key_checked_idents(group.stream(), res),
+            (TokenTree::Ident(ident), Some(TokenTree::Punct(dot)), Some(TokenTree::Ident(key)))
+                if dot.as_char() == '.' && key == "key" =>
+            {
+                res.push(ident.clone())
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_ident(token: Option<&TokenTree>, names: &[&str]) -> bool {
+    matches!(token, Some(TokenTree::Ident(i)) if names.iter().any(|n| i == n))
+}
+
+/// The field of the accounts struct `expr` refers to, e.g. `vault` for
+/// `ctx.accounts.vault`, `&mut ctx.accounts.vault.to_account_info()` or a
+/// local bound to one of those, as recorded in `bindings`.
+pub fn account_field(expr: &Expr, bindings: &[(Ident, Ident)]) -> Option<Ident> {
+    const VIEWS: &[&str] = &["to_account_info", "as_ref", "as_mut", "clone"];
+
+    match expr {
+        Expr::Field(field) => {
+            let is_accounts = match field.base.as_ref() {
+                Expr::Field(base) => matches!(&base.member, Member::Named(m) if m == "accounts"),
+                Expr::Path(base) => base.path.is_ident("accounts"),
+                _ => false,
+            };
+            match &field.member {
+                Member::Named(ident) if is_accounts => Some(ident.clone()),
+                _ => None,
+            }
+        }
+        Expr::Path(path) => {
+            let ident = path.path.get_ident()?;
+            bindings
+                .iter()
+                .rev()
+                .find(|(local, _)| local == ident)
+                .map(|(_, field)| field.clone())
+        }
+        Expr::Reference(reference) => account_field(&reference.expr, bindings),
+        Expr::Paren(paren) => account_field(&paren.expr, bindings),
+        Expr::MethodCall(call)
+            if call.args.is_empty() && VIEWS.iter().any(|v| call.method == v) =>
+        {
+            account_field(&call.receiver, bindings)
+        }
+        _ => None,
+    }
+}
+
+/// Records in `bindings` the local `let` binds to a field of the accounts
+/// struct, if it binds one, e.g. `state` for
+/// `let state = &mut ctx.accounts.state;`.
+pub fn bind_account_field(local: &Local, bindings: &mut Vec<(Ident, Ident)>) {
+    let Pat::Ident(pat) = &local.pat else {
+        return;
+    };
+    let Some((_, init)) = &local.init else {
+        return;
+    };
+    if let Some(field) = account_field(init, bindings) {
+        bindings.push((pat.ident.clone(), field));
+    }
+}
+
+/// Walks a handler in source order, collecting the fields of the accounts
+/// struct whose data is read, through `ctx.accounts` or a local bound to one.
+#[derive(Default)]
+struct DataReads {
+    bindings: Vec<(Ident, Ident)>,
+    fields: Vec<Ident>,
+}
+
+impl DataReads {
+    fn read(&mut self, account: &Expr) {
+        if let Some(field) = account_field(account, &self.bindings) {
+            if !self.fields.contains(&field) {
+                self.fields.push(field);
+            }
+        }
+    }
+}
+
+impl VisitMut for DataReads {
+    fn visit_local_mut(&mut self, node: &mut Local) {
+        visit_mut::visit_local_mut(self, node);
+        bind_account_field(node, &mut self.bindings);
+    }
+
+    fn visit_expr_field_mut(&mut self, node: &mut ExprField) {
+        visit_mut::visit_expr_field_mut(self, node);
+        if matches!(&node.member, Member::Named(m) if m == "data") {
+            self.read(&node.base);
+        }
+    }
+
+    fn visit_expr_method_call_mut(&mut self, node: &mut ExprMethodCall) {
+        const READS: &[&str] = &["try_borrow_data", "try_borrow_mut_data"];
+
+        visit_mut::visit_expr_method_call_mut(self, node);
+        if READS.iter().any(|r| node.method == r) {
+            self.read(&node.receiver);
+        }
+    }
+}
+
+/// The fields `f` of the accounts struct whose data `block` reads, e.g.
+/// through `ctx.accounts.f.try_borrow_data()`, or `a.try_borrow_data()` after
+/// `let a = &ctx.accounts.f;`.
+pub fn data_read_fields(block: &Block) -> Vec<Ident> {
+    let mut visitor = DataReads::default();
+    visitor.visit_block_mut(&mut block.clone());
+    visitor.fields
+}
+
+/// The wallet fields of `val` used as an authority: targets of `has_one`,
+/// fields with an `address` constraint and fields whose key is compared in a
+/// `constraint`.
+fn authorities(val: &AccountsStruct) -> Vec<&Ident> {
+    let mut candidates = vec![];
+    for field in val.fields.iter().filter_map(get_valid_field) {
+        let constraints = &field.constraints;
+        for has_one in constraints.has_one.iter() {
+            if let Expr::Path(path) = &has_one.join_target {
+                candidates.extend(path.path.get_ident().cloned());
+            }
+        }
+        for raw in constraints.raw.iter() {
+            key_checked_idents(raw.raw.to_token_stream(), &mut candidates);
+        }
+        if constraints.address.is_some() {
+            candidates.push(field.ident.clone());
+        }
+    }
+
+    val.fields
+        .iter()
+        .filter_map(get_valid_field)
+        .filter(|field| is_wallet(field) && candidates.contains(&field.ident))
+        .map(|field| &field.ident)
+        .collect()
+}
+
+/// Whether `val` has authorities the missing signer harness can check.
+pub fn has_authorities(val: &AccountsStruct) -> bool {
+    !authorities(val).is_empty()
+}
+
+/// Generates the helpers of the missing signer harness: the keys of the
+/// authorities of the instruction, whether a key signed it, and the assertion
+/// reporting an authority that didn't have to.
+pub fn create_signer_checks(val: &AccountsStruct) -> TokenStream {
+    let ident = &val.ident;
+    let generics = &val.generics;
+
+    let mut fields = vec![];
+    let mut composite_fields = vec![];
+    let mut signers = vec![];
+    for field in val.fields.iter() {
+        match field {
+            AccountField::Field(f) => {
+                fields.push(&f.ident);
+                if matches!(f.ty, Ty::Signer) || f.constraints.signer.is_some() {
+                    signers.push(&f.ident);
+                }
+            }
+            AccountField::CompositeField(c) => composite_fields.push(&c.ident),
+        }
+    }
+
+    let check_signers = if signers.is_empty() {
+        quote! { true }
+    } else {
+        quote! { #(self.#signers.to_account_info().is_signer)&&* }
+    };
+
+    let field_count = fields.len();
+    let authorities = authorities(val);
+    let count = authorities.len();
+    let indices = 0..count;
+    let messages = authorities.iter().map(|authority| {
+        LitStr::new(
+            &format!("Missing signer: instruction succeeded without `{authority}` signing"),
+            authority.span(),
+        )
+    });
+
+    quote! {
+        #[allow(unused_variables)]
+        impl #generics #ident #generics {
+            /// Whether the accounts anchor requires to sign did.
+            pub fn __check_signers(&self) -> bool {
+                #check_signers
+            }
+
+            pub fn __signed(&self, key: &Pubkey, remaining_accounts: &[AccountInfo]) -> bool {
+                let infos: [AccountInfo; #field_count] = [#(self.#fields.to_account_info()),*];
+                infos.iter().any(|info| info.is_signer && info.key == key)
+                    || remaining_accounts.iter().any(|info| info.is_signer && info.key == key)
+                    #(|| self.#composite_fields.__signed(key, &[]))*
+            }
+
+            pub fn __authority_keys(&self) -> [Pubkey; #count] {
+                [#(*self.#authorities.to_account_info().key),*]
+            }
+
+            pub fn __assert_signer_checked(&self, authority: usize, failed: bool) {
+                #(
+                    if authority == #indices {
+                        kani::assert(failed, #messages);
+                    }
+                )*
+            }
+        }
+    }
+}
+
+/// The raw account fields of `val` whose owner neither anchor nor a
+/// constraint checks. An `owner` constraint or a `constraint` comparing the
+/// owner with anything counts, as accounts of other programs, like oracles,
+/// are checked against their owner.
+pub fn owner_unchecked_fields(val: &AccountsStruct) -> Vec<&Ident> {
+    val.fields
+        .iter()
+        .filter_map(get_valid_field)
+        .filter(|f| {
+            matches!(f.ty, Ty::AccountInfo | Ty::UncheckedAccount)
+                && f.constraints.owner.is_none()
+                && f.constraints.address.is_none()
+                && !f
+                    .constraints
+                    .raw
+                    .iter()
+                    .any(|c| mentions_tag(c.raw.to_token_stream(), is_owner))
+        })
+        .map(|f| &f.ident)
+        .collect()
+}
+
+/// Generates `__owned_by`, telling whether a raw account field whose owner
+/// isn't checked by its constraints is owned by the program. Other fields are
+/// always considered owned.
+pub fn create_owner_checks(val: &AccountsStruct) -> TokenStream {
+    let ident = &val.ident;
+    let generics = &val.generics;
+    let fields = owner_unchecked_fields(val);
+    let names = fields.iter().map(|f| f.to_string());
+
+    quote! {
+        #[allow(unused_variables)]
+        impl #generics #ident #generics {
+            pub fn __owned_by(&self, field: &str, program_id: &Pubkey) -> bool {
+                match field {
+                    #(#names => self.#fields.to_account_info().owner == program_id,)*
+                    _ => true,
+                }
+            }
+        }
+    }
+}
+
+/// Generates the checks of the `seeds` constraints: `__check_seeds`, telling
+/// whether the PDAs match their seeds as anchor checks, which uses the bumps
+/// it is given, and `__stored_bumps_canonical`, telling whether the bumps read
+/// from accounts are the canonical ones.
+pub fn create_bump_checks(
+    val: &AccountsStruct,
+    arg_names: &[Ident],
+    arg_types: &[Type],
+) -> TokenStream {
+    let ident = &val.ident;
+    let generics = &val.generics;
+    let fields = val
+        .fields
+        .iter()
+        .filter_map(get_valid_field)
+        .map(|f| &f.ident)
+        .collect::<Vec<_>>();
+
+    let mut seed_checks = vec![];
+    let mut stored_bumps = vec![];
+    for field in val.fields.iter().filter_map(get_valid_field) {
+        let Some(group) = &field.constraints.seeds else {
+            continue;
+        };
+        let key = &field.ident;
+        let seeds = group.seeds.iter().collect::<Vec<_>>();
+        let program = match &group.program_seed {
+            Some(program) => quote! { &(#program) },
+            None => quote! { program_id },
+        };
+
+        match &group.bump {
+            Some(bump) => {
+                // The seeds are passed inline, so that the temporaries they
+                // borrow live until the comparison, and each check is
+                // parenthesized, so that joining them with `&&` stays an
+                // expression.
+                seed_checks.push(quote! {(
+                    Pubkey::create_program_address(&[#(#seeds,)* &[#bump]], #program)
+                        == Some(*#key.to_account_info().key)
+                )});
+                if let Expr::Field(_) = bump {
+                    stored_bumps.push(quote! {(
+                        #bump == Pubkey::find_program_address(&[#(#seeds),*], #program).1
+                    )});
+                }
+            }
+            None => seed_checks.push(quote! {(
+                Pubkey::find_program_address(&[#(#seeds),*], #program).0
+                    == *#key.to_account_info().key
+            )}),
+        }
+    }
+
+    let seed_checks = if seed_checks.is_empty() {
+        quote! { true }
+    } else {
+        quote! { #(#seed_checks)&&* }
+    };
+    let stored_bumps = if stored_bumps.is_empty() {
+        quote! { true }
+    } else {
+        quote! { #(#stored_bumps)&&* }
+    };
+
+    quote! {
+        #[allow(unused_variables)]
+        impl #generics #ident #generics {
+            pub fn __check_seeds(&self, program_id: &Pubkey, #(#arg_names: #arg_types),*) -> bool {
+                #(let #fields = &self.#fields;)*
+                #seed_checks
+            }
+
+            pub fn __stored_bumps_canonical(
+                &self,
+                program_id: &Pubkey,
+                #(#arg_names: #arg_types),*
+            ) -> bool {
+                #(let #fields = &self.#fields;)*
+                #stored_bumps
+            }
+        }
+    }
+}
+
+/// The longest seed solana accepts.
+const MAX_SEED_LEN: usize = 32;
+
+/// The length of the range `start..end` indexing a seed, if both ends are
+/// literals.
+fn range_len(index: &Expr) -> Option<usize> {
+    let int = |e: &Option<Box<Expr>>, default: usize| match e.as_deref() {
+        None => Some(default),
+        Some(Expr::Lit(lit)) => match &lit.lit {
+            Lit::Int(i) => i.base10_parse::<usize>().ok(),
+            _ => None,
+        },
+        Some(_) => None,
+    };
+    let Expr::Range(range) = index else {
+        return None;
+    };
+    let start = int(&range.from, 0)?;
+    let end = int(&range.to, usize::MAX)?;
+    let end = match range.limits {
+        RangeLimits::HalfOpen(_) => end,
+        RangeLimits::Closed(_) => end.checked_add(1)?,
+    };
+    (range.to.is_some() && start <= end).then(|| end - start)
+}
+
+/// Classifies a seed of a `seeds` constraint for the collision check: a
+/// literal is constant, keys, arrays and literal ranges have a fixed length,
+/// the bytes of an int the length of its type, found by `int_len`, and the
+/// length of anything else is only bounded by the longest seed.
+fn seed_schema(seed: &Expr, int_len: &dyn Fn(&Expr) -> TokenStream) -> TokenStream {
+    let var = |min_len: &dyn ToTokens, max_len: &dyn ToTokens| {
+        quote! { seeds::Seed::Var { min_len: #min_len, max_len: #max_len } }
+    };
+    let any = || var(&0usize, &MAX_SEED_LEN);
+    match seed {
+        Expr::Lit(lit) => match &lit.lit {
+            Lit::ByteStr(bytes) => quote! { seeds::Seed::Const(#bytes) },
+            Lit::Str(s) => {
+                let bytes = LitByteStr::new(s.value().as_bytes(), s.span());
+                quote! { seeds::Seed::Const(#bytes) }
+            }
+            _ => any(),
+        },
+        Expr::Reference(r) => seed_schema(&r.expr, int_len),
+        Expr::Paren(p) => seed_schema(&p.expr, int_len),
+        Expr::Index(i) => match range_len(&i.index) {
+            Some(len) => var(&len, &len),
+            None => any(),
+        },
+        Expr::Array(a) => var(&a.elems.len(), &a.elems.len()),
+        Expr::Field(f) if f.member.to_token_stream().to_string() == "key" => {
+            var(&32usize, &32usize)
+        }
+        Expr::MethodCall(m) => match m.method.to_string().as_str() {
+            "as_ref" | "as_slice" | "as_bytes" => seed_schema(&m.receiver, int_len),
+            "key" | "to_bytes" => var(&32usize, &32usize),
+            "to_le_bytes" | "to_be_bytes" | "to_ne_bytes" => {
+                let len = int_len(seed);
+                var(&len, &len)
+            }
+            _ => any(),
+        },
+        _ => any(),
+    }
+}
+
+/// Generates `__SEED_SCHEMAS`, the seeds of the PDA fields of `val`, checked
+/// for collisions with the other accounts structs of the program.
+pub fn create_seed_schemas(
+    val: &AccountsStruct,
+    arg_names: &[Ident],
+    arg_types: &[Type],
+) -> TokenStream {
+    let ident = &val.ident;
+    let generics = &val.generics;
+    let accounts = ident.to_string();
+    let fields = val
+        .fields
+        .iter()
+        .filter_map(get_valid_field)
+        .map(|f| &f.ident)
+        .collect::<Vec<_>>();
+
+    // The length of the bytes of an int is read off the type of the seed,
+    // written in a closure seeing the same names as the constraint.
+    let int_len = |seed: &Expr| {
+        quote! {
+            seeds::bytes_len(&|(__accounts, (#(#arg_names,)*)): (&Self, (#(#arg_types,)*))| {
+                #(let #fields = &__accounts.#fields;)*
+                #seed
+            })
+        }
+    };
+
+    let schemas = val
+        .fields
+        .iter()
+        .filter_map(get_valid_field)
+        .filter_map(|field| {
+            let group = field.constraints.seeds.as_ref()?;
+            let name = field.ident.to_string();
+            let ty = match &field.ty {
+                Ty::Account(account) => account.account_type_path.to_token_stream().to_string(),
+                _ => String::new(),
+            };
+            let custom_program = group.program_seed.is_some();
+            let seeds = group.seeds.iter().map(|seed| seed_schema(seed, &int_len));
+            Some(quote! {
+                seeds::SeedSchema {
+                    accounts: #accounts,
+                    field: #name,
+                    ty: #ty,
+                    custom_program: #custom_program,
+                    seeds: &[#(#seeds),*],
+                }
+            })
+        });
+
+    quote! {
+        #[allow(unused_variables)]
+        impl #generics #ident #generics {
+            pub const __SEED_SCHEMAS: &'static [seeds::SeedSchema] = &[#(#schemas),*];
+        }
+    }
+}
+
+/// Whether `field` is an account holding state, i.e. one the same account
+/// could be passed as twice, unlike programs and sysvars with a fixed key.
+fn holds_state(field: &Field) -> bool {
+    is_wallet(field) || matches!(field.ty, Ty::Account(_))
+}
+
+/// Generates the helpers of key aliasing: `__share_aliased_state`, making the
+/// fields with the same key the same account when aliasing is enabled and
+/// ruling them out otherwise, which every harness does, and the checks of the
+/// duplicate mutable account harness. Accounts are indexed by their position
+/// among the ones holding state.
+pub fn create_alias_checks(val: &AccountsStruct) -> TokenStream {
+    let ident = &val.ident;
+    let generics = &val.generics;
+    let fields = val
+        .fields
+        .iter()
+        .filter_map(get_valid_field)
+        .filter(|f| holds_state(f))
+        .collect::<Vec<_>>();
+    let count = fields.len();
+    let idents = fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
+    let indices = 0..count;
+    let lifetime = generics
+        .lifetimes()
+        .next()
+        .map(|l| l.lifetime.to_token_stream())
+        .unwrap_or(quote! { '_ });
+    let (data_indices, data_fields): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| matches!(f.ty, Ty::Account(_)))
+        .map(|(i, f)| (i, &f.ident))
+        .unzip();
+
+    let mutable = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| f.constraints.mutable.is_some() || f.constraints.init.is_some())
+        .collect::<Vec<_>>();
+    let mutable_count = mutable.len();
+    let mutable_indices = mutable.iter().map(|(i, _)| i);
+    let mut pair_checks = vec![];
+    for (k, (i, first)) in mutable.iter().enumerate() {
+        for (j, second) in mutable[k + 1..].iter() {
+            let message = LitStr::new(
+                &format!(
+                    "Duplicate mutable account: instruction succeeded with `{}` and `{}` as the same account, leaving them different",
+                    first.ident, second.ident
+                ),
+                first.ident.span(),
+            );
+            pair_checks.push(quote! {
+                if first == #i && second == #j {
+                    kani::assert(agree, #message);
+                }
+            });
+        }
+    }
+
+    quote! {
+        #[allow(unused_variables)]
+        impl #generics #ident #generics {
+            pub fn __account_infos(&self) -> [AccountInfo<#lifetime>; #count] {
+                [#(self.#idents.to_account_info()),*]
+            }
+
+            /// The serialized data of `Account` fields, which the info doesn't
+            /// hold.
+            pub fn __account_data(&self, index: usize) -> Option<std::vec::Vec<u8>> {
+                match index {
+                    #(#data_indices => self.#data_fields.account.try_to_vec().ok(),)*
+                    _ => None,
+                }
+            }
+
+            fn __set_account_info(&mut self, index: usize, info: AccountInfo<#lifetime>) {
+                match index {
+                    #(#indices => self.#idents.set_account_info(info),)*
+                    _ => {}
+                }
+            }
+
+            /// Whether the `Account` fields `first` and `second`, if both are
+            /// ones, hold the same data.
+            pub fn __same_data(&self, first: usize, second: usize) -> bool {
+                match (self.__account_data(first), self.__account_data(second)) {
+                    (Some(a), Some(b)) => a == b,
+                    _ => true,
+                }
+            }
+
+            /// Makes every field the same account as the first field with
+            /// its key when aliasing is enabled, the fields sharing its info
+            /// and starting with the same data. Without aliasing, as in
+            /// every harness but the duplicate mutable account one, fields
+            /// with the same key are ruled out.
+            pub fn __share_aliased_state(&mut self) {
+                let infos = self.__account_infos();
+                for j in 0..#count {
+                    let Some(i) = (0..j).find(|&i| infos[i].key == infos[j].key) else {
+                        continue;
+                    };
+                    if solana_program::pubkey::aliasing() {
+                        self.__set_account_info(j, infos[i]);
+                        kani::assume(self.__same_data(i, j));
+                    } else {
+                        kani::assume(false);
+                    }
+                }
+            }
+
+            /// Whether `first` and `second` are different mutable fields
+            /// holding the same account.
+            pub fn __mutable_aliases(&self, first: usize, second: usize) -> bool {
+                let mutable: [usize; #mutable_count] = [#(#mutable_indices),*];
+                first < second
+                    && mutable.contains(&first)
+                    && mutable.contains(&second)
+                    && self.__account_infos()[first].key == self.__account_infos()[second].key
+            }
+
+            /// The fields share the info of the account, but `Account`
+            /// fields each hold data of their own, as in anchor.
+            pub fn __assert_aliases_agree(&self, first: usize, second: usize) {
+                let agree = self.__same_data(first, second);
+                #(#pair_checks)*
+            }
+        }
+    }
+}
+
+/// Whether `ident` names the owner or the discriminator of an account.
+fn is_type_tag(ident: &Ident) -> bool {
+    is_owner(ident) || ident.to_string().to_lowercase().contains("discriminator")
+}
+
+fn is_owner(ident: &Ident) -> bool {
+    ident == "owner"
+}
+
+/// Whether `tokens` mention an identifier `is_tag` accepts.
+fn mentions_tag(tokens: TokenStream, is_tag: fn(&Ident) -> bool) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Group(group) => mentions_tag(group.stream(), is_tag),
+        TokenTree::Ident(ident) => is_tag(&ident),
+        _ => false,
+    })
+}
+
+/// Collects the fields of the accounts struct an expression mentions, e.g.
+/// `vault` in `info.owner != ctx.program_id` after `let info = &ctx.accounts.vault;`.
+struct Mentions<'a> {
+    bindings: &'a [(Ident, Ident)],
+    fields: Vec<Ident>,
+}
+
+impl VisitMut for Mentions<'_> {
+    fn visit_expr_mut(&mut self, node: &mut Expr) {
+        match account_field(node, self.bindings) {
+            Some(field) if !self.fields.contains(&field) => self.fields.push(field),
+            Some(_) => {}
+            None => visit_mut::visit_expr_mut(self, node),
+        }
+    }
+}
+
+fn mentioned_fields<'a>(
+    exprs: impl IntoIterator<Item = &'a Expr>,
+    bindings: &[(Ident, Ident)],
+) -> Vec<Ident> {
+    let mut visitor = Mentions {
+        bindings,
+        fields: vec![],
+    };
+    for expr in exprs {
+        visitor.visit_expr_mut(&mut expr.clone());
+    }
+    visitor.fields
+}
+
+/// Walks a handler in source order, collecting the accounts whose tag, which
+/// `is_tag` names, is compared with anything, and the ones whose data is
+/// deserialized before that. Locals computed from a single account, like its
+/// data, stand for that account.
+struct TagChecks {
+    is_tag: fn(&Ident) -> bool,
+    bindings: Vec<(Ident, Ident)>,
+    checked: Vec<Ident>,
+    unchecked: Vec<Ident>,
+}
+
+impl TagChecks {
+    fn walk(block: &Block, is_tag: fn(&Ident) -> bool) -> Self {
+        let mut visitor = Self {
+            is_tag,
+            bindings: vec![],
+            checked: vec![],
+            unchecked: vec![],
+        };
+        visitor.visit_block_mut(&mut block.clone());
+        visitor
+    }
+
+    fn check<'a>(&mut self, exprs: impl IntoIterator<Item = &'a Expr>) {
+        let fields = mentioned_fields(exprs, &self.bindings);
+        self.checked.extend(fields);
+    }
+}
+
+impl VisitMut for TagChecks {
+    fn visit_local_mut(&mut self, node: &mut Local) {
+        visit_mut::visit_local_mut(self, node);
+        let pat = match &node.pat {
+            Pat::Type(typed) => typed.pat.as_ref(),
+            pat => pat,
+        };
+        let (Pat::Ident(pat), Some((_, init))) = (pat, &node.init) else {
+            return;
+        };
+        if let [field] = mentioned_fields([init.as_ref()], &self.bindings).as_slice() {
+            self.bindings.push((pat.ident.clone(), field.clone()));
+        }
+    }
+
+    fn visit_expr_binary_mut(&mut self, node: &mut ExprBinary) {
+        visit_mut::visit_expr_binary_mut(self, node);
+        if matches!(node.op, BinOp::Eq(_) | BinOp::Ne(_))
+            && mentions_tag(node.to_token_stream(), self.is_tag)
+        {
+            self.check([node.left.as_ref(), node.right.as_ref()]);
+        }
+    }
+
+    fn visit_expr_method_call_mut(&mut self, node: &mut ExprMethodCall) {
+        visit_mut::visit_expr_method_call_mut(self, node);
+        if node.method == "starts_with" && mentions_tag(node.args.to_token_stream(), self.is_tag) {
+            self.check([node.receiver.as_ref()]);
+        }
+    }
+
+    fn visit_macro_mut(&mut self, node: &mut Macro) {
+        const CHECKS: &[&str] = &[
+            "require",
+            "require_eq",
+            "require_neq",
+            "require_keys_eq",
+            "require_keys_neq",
+            "assert",
+            "assert_eq",
+            "assert_ne",
+        ];
+        let is_check = node
+            .path
+            .get_ident()
+            .is_some_and(|i| CHECKS.iter().any(|c| i == c));
+        if !is_check {
+            return;
+        }
+        let Ok(args) = node.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) else {
+            return;
+        };
+        for arg in args.iter() {
+            self.visit_expr_mut(&mut arg.clone());
+        }
+        if mentions_tag(node.tokens.clone(), self.is_tag) {
+            self.check(args.iter());
+        }
+    }
+
+    fn visit_expr_call_mut(&mut self, node: &mut ExprCall) {
+        const UNCHECKED: &[&str] = &["try_deserialize_unchecked", "try_from_slice", "deserialize"];
+
+        visit_mut::visit_expr_call_mut(self, node);
+        if let Expr::Path(ExprPath { path, .. }) = node.func.as_ref() {
+            let is_unchecked = path.segments.len() > 1
+                && path
+                    .segments
+                    .last()
+                    .is_some_and(|s| UNCHECKED.iter().any(|u| s.ident == u));
+            if !is_unchecked {
+                return;
+            }
+            for field in mentioned_fields(node.args.iter(), &self.bindings) {
+                if !self.checked.contains(&field) && !self.unchecked.contains(&field) {
+                    self.unchecked.push(field);
+                }
+            }
+        }
+    }
+}
+
+/// The fields of the accounts struct whose data `block` deserializes
+/// without checking their discriminator or owner first, e.g. `vault` for
+/// `Vault::try_from_slice(&ctx.accounts.vault.try_borrow_data()?)`.
+pub fn unchecked_deserialize_fields(block: &Block) -> Vec<Ident> {
+    TagChecks::walk(block, is_type_tag).unchecked
+}
+
+/// The fields of the accounts struct whose owner `block` compares with
+/// anything, e.g. `oracle` for `require_keys_eq!(*oracle.owner, PYTH_ID)`.
+pub fn owner_checked_fields(block: &Block) -> Vec<Ident> {
+    TagChecks::walk(block, is_owner).checked
+}
+
+/// Whether `tokens` read sysvar data without the key of the account being
+/// checked, e.g. with `load_instruction_at` or `deserialize_data`. Reads
+/// checking it, like `Clock::from_account_info`, can't be spoofed.
+pub fn reads_sysvars(tokens: TokenStream) -> bool {
+    const READS: &[&str] = &[
+        "load_current_index",
+        "load_instruction_at",
+        "deserialize_data",
+    ];
+
+    tokens.into_iter().any(|token| match &token {
+        TokenTree::Group(group) => reads_sysvars(group.stream()),
+        _ => is_ident(Some(&token), READS),
+    })
+}
+
+/// Whether `val` derives PDAs from `seeds` constraints. Composite fields are
+/// assumed to.
+pub fn derives_pdas(val: &AccountsStruct) -> bool {
+    val.fields.iter().any(|field| match field {
+        AccountField::Field(f) => f.constraints.seeds.is_some(),
+        AccountField::CompositeField(_) => true,
+    })
+}
+
+/// Whether `tokens` derive addresses from bumps, e.g. with
+/// `create_program_address` or by signing a CPI with seeds.
+pub fn uses_bumps(tokens: TokenStream) -> bool {
+    const DERIVES: &[&str] = &["create_program_address", "invoke_signed", "new_with_signer"];
+
+    tokens.into_iter().any(|token| match &token {
+        TokenTree::Group(group) => uses_bumps(group.stream()),
+        _ => is_ident(Some(&token), DERIVES),
+    })
+}
+
+/// Generates `__account_types`, the types of the `Account` fields of `val`
+/// checked for type cosplay, and `__is_raw`, telling whether a field is a raw
+/// account whose data no discriminator check protects.
+pub fn create_cosplay_checks(val: &AccountsStruct) -> TokenStream {
+    let ident = &val.ident;
+    let generics = &val.generics;
+    let types = val
+        .fields
+        .iter()
+        .filter_map(get_valid_field)
+        .filter_map(|f| match &f.ty {
+            Ty::Account(account) => Some(&account.account_type_path),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let raw = val
+        .fields
+        .iter()
+        .filter_map(get_valid_field)
+        .filter(|f| matches!(f.ty, Ty::AccountInfo | Ty::UncheckedAccount))
+        .map(|f| f.ident.to_string())
+        .collect::<Vec<_>>();
+    let is_raw = if raw.is_empty() {
+        quote! { false }
+    } else {
+        quote! { matches!(field, #(#raw)|*) }
+    };
+
+    quote! {
+        #[allow(unused_variables)]
+        impl #generics #ident #generics {
+            pub fn __account_types() -> std::vec::Vec<account::AccountType> {
+                vec![#(
+                    account::AccountType {
+                        type_name: std::any::type_name::<#types>(),
+                        discriminator: <#types as AccountDeserialize>::discriminator(),
+                        accepts: Account::<#types>::check_discriminator,
+                    }
+                ),*]
+            }
+
+            pub fn __is_raw(&self, field: &str) -> bool {
+                #is_raw
+            }
+        }
+    }
+}
+
+/// Generates the checks of the accounts initialized with `init_if_needed`,
+/// which may exist already: `__existing_accounts`, telling which ones do,
+/// `__assert_not_reinitialized`, reporting the ones an instruction changed
+/// the guarded fields of, and `__assert_no_unconditional_reinit`, reporting
+/// guarded fields the handler assigns to whether the account existed or not,
+/// given as pairs of an accounts struct field and a field of its data.
+/// The targets of their `has_one` constraints are guarded too.
+pub fn create_reinit_checks(val: &AccountsStruct) -> TokenStream {
+    let ident = &val.ident;
+    let generics = &val.generics;
+    let snapshot = snapshot_ident(val);
+    let fields = val
+        .fields
+        .iter()
+        .filter_map(get_valid_field)
+        .filter(|f| {
+            matches!(f.ty, Ty::Account(_))
+                && f.constraints.init.as_ref().is_some_and(|i| i.if_needed)
+        })
+        .collect::<Vec<_>>();
+    let count = fields.len();
+    let idents = fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
+
+    let mut reinit_asserts = vec![];
+    let mut assignment_asserts = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let f = &field.ident;
+        let has_one = field
+            .constraints
+            .has_one
+            .iter()
+            .filter_map(|h| match &h.join_target {
+                Expr::Path(path) => path.path.get_ident(),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let has_one_names = has_one.iter().map(|h| h.to_string());
+        let message = LitStr::new(
+            &format!("Reinitialization: instruction changed the authority or immutable fields of existing `{f}`"),
+            f.span(),
+        );
+        reinit_asserts.push(quote! {
+            kani::assert(
+                failed
+                    || !existed[#i]
+                    || (self.#f.account._check_reinit(&before.#f)
+                        #(&& self.#f.account.#has_one == before.#f.#has_one)*),
+                #message,
+            );
+        });
+        let message = LitStr::new(
+            &format!("Reinitialization: handler unconditionally assigns to an authority or immutable field of `{f}`"),
+            f.span(),
+        );
+        let name = f.to_string();
+        assignment_asserts.push(quote! {
+            kani::assert(
+                !assigned.iter().any(|(account, a)| {
+                    *account == #name
+                        && (self.#f.account._guarded_fields().contains(a)
+                            #(|| *a == #has_one_names)*)
+                }),
+                #message,
+            );
+        });
+    }
+
+    // Account types that aren't `#[account]`, e.g. token accounts, have no
+    // guarded fields.
+    let fallback = quote! {
+        trait __NoReinit {
+            fn _guarded_fields(&self) -> &'static [&'static str] {
+                &[]
+            }
+
+            fn _check_reinit(&self, _before: &Self) -> bool {
+                true
+            }
+        }
+        impl<T> __NoReinit for T {}
+    };
+
+    quote! {
+        #[allow(unused_variables)]
+        impl #generics #ident #generics {
+            /// Whether the `init_if_needed` accounts existed, rather than
+            /// being created by the instruction.
+            pub fn __existing_accounts(&self, program_id: &Pubkey) -> [bool; #count] {
+                [#(
+                    self.#idents.to_account_info().owner == program_id
+                        && self.#idents.to_account_info().lamports() > 0
+                ),*]
+            }
+
+            pub fn __assert_not_reinitialized(
+                &self,
+                before: &#snapshot,
+                existed: &[bool; #count],
+                failed: bool,
+            ) {
+                #fallback
+                #(#reinit_asserts)*
+            }
+
+            pub fn __assert_no_unconditional_reinit(&self, assigned: &[(&str, &str)]) {
+                #fallback
+                #(#assignment_asserts)*
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_checked_idents() {
+        let mut res = vec![];
+        key_checked_idents(
+            quote! { admin.key() == config.account.admin && (*payer.key == ID) },
+            &mut res,
+        );
+        assert_eq!(res, ["admin", "payer"]);
+    }
+
+    #[test]
+    fn test_data_read_fields() {
+        let fields = |block: Block| {
+            data_read_fields(&block)
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            fields(syn::parse_quote! {{
+                let data = ctx.accounts.oracle.try_borrow_data()?;
+                let len = ctx.accounts.pool.to_account_info().data.len();
+                let key = ctx.accounts.admin.key();
+            }}),
+            ["oracle", "pool"]
+        );
+        assert_eq!(
+            fields(syn::parse_quote! {{
+                let feed = &ctx.accounts.feed;
+                let info = feed.to_account_info();
+                let price = read_price(&info.try_borrow_data()?);
+            }}),
+            ["feed"]
+        );
+    }
+
+    #[test]
+    fn test_seed_schema() {
+        let int_len = |seed: &Expr| quote! { bytes_len(#seed) };
+        let schema = |seed: Expr| seed_schema(&seed, &int_len).to_string();
+        assert_eq!(
+            schema(syn::parse_quote!(b"vault".as_ref())),
+            quote! { seeds::Seed::Const(b"vault") }.to_string()
+        );
+        assert_eq!(
+            schema(syn::parse_quote!(pool.key().as_ref())),
+            quote! { seeds::Seed::Var { min_len: 32usize, max_len: 32usize } }.to_string()
+        );
+        assert_eq!(
+            schema(syn::parse_quote!(&[vault.bump])),
+            quote! { seeds::Seed::Var { min_len: 1usize, max_len: 1usize } }.to_string()
+        );
+        assert_eq!(
+            schema(syn::parse_quote!(name.as_bytes())),
+            quote! { seeds::Seed::Var { min_len: 0usize, max_len: 32usize } }.to_string()
+        );
+        assert_eq!(
+            schema(syn::parse_quote!(&name.as_bytes()[..8])),
+            quote! { seeds::Seed::Var { min_len: 8usize, max_len: 8usize } }.to_string()
+        );
+        assert_eq!(
+            schema(syn::parse_quote!(&name.as_bytes()[offset..])),
+            quote! { seeds::Seed::Var { min_len: 0usize, max_len: 32usize } }.to_string()
+        );
+        assert_eq!(
+            schema(syn::parse_quote!(pool.index.to_le_bytes().as_ref())),
+            quote! {
+                seeds::Seed::Var {
+                    min_len: bytes_len(pool.index.to_le_bytes()),
+                    max_len: bytes_len(pool.index.to_le_bytes())
+                }
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_unchecked_deserialize_fields() {
+        let fields = |block: Block| {
+            unchecked_deserialize_fields(&block)
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            fields(syn::parse_quote! {{
+                let data = ctx.accounts.vault.try_borrow_data()?;
+                let vault = Vault::try_from_slice(&data[8..])?;
+            }}),
+            ["vault"]
+        );
+        assert!(fields(syn::parse_quote! {{
+            let data = ctx.accounts.vault.try_borrow_data()?;
+            let vault = Vault::try_deserialize(&mut &data[..])?;
+        }})
+        .is_empty());
+        assert!(fields(syn::parse_quote! {{
+            let data = ctx.accounts.vault.try_borrow_data()?;
+            require!(data.starts_with(&Vault::DISCRIMINATOR), VaultError::Cosplay);
+            let vault = Vault::try_from_slice(&data[8..])?;
+        }})
+        .is_empty());
+        assert!(fields(syn::parse_quote! {{
+            let info = ctx.accounts.vault.to_account_info();
+            if info.owner != ctx.program_id {
+                return err!(VaultError::Cosplay);
+            }
+            let vault = Vault::try_from_slice(&info.try_borrow_data()?[8..])?;
+        }})
+        .is_empty());
+        assert_eq!(
+            fields(syn::parse_quote! {{
+                let info = ctx.accounts.vault.to_account_info();
+                let vault = Vault::try_from_slice(&info.try_borrow_data()?[8..])?;
+                require_keys_eq!(*info.owner, crate::ID);
+            }}),
+            ["vault"]
+        );
+        // Checking one account doesn't make the data of another one safe.
+        assert_eq!(
+            fields(syn::parse_quote! {{
+                require_keys_eq!(*ctx.accounts.config.owner, crate::ID);
+                let data = ctx.accounts.vault.try_borrow_data()?;
+                let vault = Vault::try_from_slice(&data[8..])?;
+            }}),
+            ["vault"]
+        );
+    }
+
+    #[test]
+    fn test_owner_checked_fields() {
+        let fields = |block: Block| {
+            owner_checked_fields(&block)
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            fields(syn::parse_quote! {{
+                let oracle = &ctx.accounts.oracle;
+                require_keys_eq!(*oracle.owner, PYTH_ID);
+                let price = Price::try_from_slice(&oracle.try_borrow_data()?)?;
+            }}),
+            ["oracle"]
+        );
+        assert_eq!(
+            fields(syn::parse_quote! {{
+                if ctx.accounts.vault.owner != ctx.program_id {
+                    return err!(VaultError::Owner);
+                }
+            }}),
+            ["vault"]
+        );
+        assert!(fields(syn::parse_quote! {{
+            let data = ctx.accounts.vault.try_borrow_data()?;
+            require!(data.starts_with(&Vault::DISCRIMINATOR), VaultError::Cosplay);
+        }})
+        .is_empty());
+    }
+
+    #[test]
+    fn test_account_field() {
+        let mut bindings = vec![];
+        let local: syn::Stmt = syn::parse_quote! { let state = &mut ctx.accounts.state; };
+        let syn::Stmt::Local(local) = local else {
+            unreachable!()
+        };
+        bind_account_field(&local, &mut bindings);
+        let field = |expr: Expr| account_field(&expr, &bindings).map(|f| f.to_string());
+        assert_eq!(field(syn::parse_quote!(state)).as_deref(), Some("state"));
+        assert_eq!(
+            field(syn::parse_quote!(ctx.accounts.vault.to_account_info())).as_deref(),
+            Some("vault")
+        );
+        assert_eq!(field(syn::parse_quote!(ctx.program_id)), None);
+        assert_eq!(field(syn::parse_quote!(other)), None);
+    }
+
+    #[test]
+    fn test_reads_sysvars() {
+        // Each of these reads a sysvar the caller can forge.
+        assert!(reads_sysvars(quote! {
+            let index = load_current_index(&ctx.accounts.instructions.data.borrow());
+        }));
+        assert!(reads_sysvars(quote! {
+            let ix = load_instruction_at(0, &ctx.accounts.instructions.data.borrow())?;
+        }));
+        assert!(reads_sysvars(quote! {
+            let clock: Clock = ctx.accounts.clock.deserialize_data()?;
+        }));
+        assert!(!reads_sysvars(quote! {
+            let clock = Clock::from_account_info(&ctx.accounts.clock)?;
+            let ix = load_instruction_at_checked(0, &ctx.accounts.instructions)?;
+        }));
+    }
+}
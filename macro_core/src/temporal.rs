@@ -0,0 +1,136 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Fields, Ident, ItemStruct, Type};
+
+/// How a field of an `#[account]` type may change across an instruction.
+pub enum Temporal {
+    /// `#[immutable]`: never changes after initialization.
+    Immutable,
+    /// `#[write_once]`: may go from its default value to another value once.
+    WriteOnce,
+    /// `#[monotonic(increasing)]` or `#[monotonic(decreasing)]`.
+    Monotonic { increasing: bool },
+}
+
+impl Temporal {
+    fn check(&self, field: &Ident) -> TokenStream {
+        match self {
+            Temporal::Immutable => quote! {
+                self.#field == before.#field
+            },
+            Temporal::WriteOnce => quote! {
+                before.#field == Default::default() || self.#field == before.#field
+            },
+            Temporal::Monotonic { increasing: true } => quote! {
+                self.#field >= before.#field
+            },
+            Temporal::Monotonic { increasing: false } => quote! {
+                self.#field <= before.#field
+            },
+        }
+    }
+}
+
+/// Removes the temporal annotations from the fields of `item` and returns them
+/// alongside the field they were placed on.
+pub fn take_temporal(item: &mut ItemStruct) -> syn::Result<Vec<(Ident, Temporal)>> {
+    let mut res = vec![];
+    let Fields::Named(fields) = &mut item.fields else {
+        return Ok(res);
+    };
+
+    for field in fields.named.iter_mut() {
+        let ident = field.ident.clone().unwrap();
+        for attr in std::mem::take(&mut field.attrs).into_iter() {
+            if attr.path.is_ident("immutable") {
+                res.push((ident.clone(), Temporal::Immutable));
+            } else if attr.path.is_ident("write_once") {
+                res.push((ident.clone(), Temporal::WriteOnce));
+            } else if attr.path.is_ident("monotonic") {
+                let direction = attr.parse_args::<Ident>()?;
+                let increasing = if direction == "increasing" {
+                    true
+                } else if direction == "decreasing" {
+                    false
+                } else {
+                    return Err(syn::Error::new_spanned(
+                        direction,
+                        "expected `increasing` or `decreasing`",
+                    ));
+                };
+                res.push((ident.clone(), Temporal::Monotonic { increasing }));
+            } else {
+                field.attrs.push(attr);
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// Generates `_check_temporal` for an `#[account]` type with annotated fields.
+pub fn temporal_checks(item: &ItemStruct, fields: &[(Ident, Temporal)]) -> TokenStream {
+    if fields.is_empty() {
+        return quote! {};
+    }
+
+    let ident = &item.ident;
+    let checks = fields.iter().map(|(field, temporal)| temporal.check(field));
+    quote! {
+        impl #ident {
+            pub fn _check_temporal(&self, before: &Self) -> bool {
+                #(
+                    if !(#checks) {
+                        return false;
+                    }
+                )*
+                true
+            }
+        }
+    }
+}
+
+/// Whether `field` holds the authority of the account, i.e. a `Pubkey` with
+/// one of the names authorities go by.
+fn is_authority(field: &Ident, ty: &Type) -> bool {
+    const NAMES: &[&str] = &["authority", "owner", "admin"];
+
+    let Type::Path(tp) = ty else {
+        return false;
+    };
+    let name = field.to_string();
+    tp.path.segments.last().is_some_and(|s| s.ident == "Pubkey") && NAMES.iter().any(|n| name == *n)
+}
+
+/// Generates the checks of an `#[account]` type initialized again through
+/// `init_if_needed`: `_guarded_fields`, the fields an existing account has
+/// to keep, its `#[immutable]` ones and authorities, and `_check_reinit`,
+/// telling whether it kept them.
+pub fn reinit_checks(item: &ItemStruct, temporal: &[(Ident, Temporal)]) -> TokenStream {
+    let mut guarded = temporal
+        .iter()
+        .filter(|(_, t)| matches!(t, Temporal::Immutable))
+        .map(|(field, _)| field.clone())
+        .collect::<Vec<_>>();
+    if let Fields::Named(fields) = &item.fields {
+        for field in fields.named.iter() {
+            let ident = field.ident.as_ref().unwrap();
+            if is_authority(ident, &field.ty) && !guarded.contains(ident) {
+                guarded.push(ident.clone());
+            }
+        }
+    }
+
+    let ident = &item.ident;
+    let names = guarded.iter().map(|f| f.to_string());
+    quote! {
+        impl #ident {
+            pub fn _guarded_fields(&self) -> &'static [&'static str] {
+                &[#(#names),*]
+            }
+
+            pub fn _check_reinit(&self, before: &Self) -> bool {
+                true #(&& self.#guarded == before.#guarded)*
+            }
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Attribute, Fields, Ident, ItemStruct, Token,
+};
+
+/// A single `From -> To` edge of a state machine.
+pub struct Transition {
+    pub from: Ident,
+    pub to: Ident,
+}
+
+impl Parse for Transition {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let from = input.parse()?;
+        input.parse::<Token![->]>()?;
+        let to = input.parse()?;
+        Ok(Self { from, to })
+    }
+}
+
+/// The contents of `#[transitions(field: A -> B, B -> C)]`.
+pub struct TransitionSpec {
+    pub field: Ident,
+    pub transitions: Vec<Transition>,
+}
+
+impl Parse for TransitionSpec {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let field = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let transitions = Punctuated::<Transition, Token![,]>::parse_terminated(input)?
+            .into_iter()
+            .collect();
+        Ok(Self { field, transitions })
+    }
+}
+
+impl TransitionSpec {
+    /// The allowed edges as a `&[(&str, &str)]` expression.
+    pub fn allowed(&self) -> TokenStream {
+        let edges = self.transitions.iter().map(|t| {
+            let from = t.from.to_string();
+            let to = t.to.to_string();
+            quote! { (#from, #to) }
+        });
+        quote! { &[#(#edges),*] }
+    }
+}
+
+/// Removes every `#[transitions(...)]` attribute from `attrs` and parses it.
+pub fn take_transitions(attrs: &mut Vec<Attribute>) -> syn::Result<Vec<TransitionSpec>> {
+    let mut specs = vec![];
+    for attr in std::mem::take(attrs).into_iter() {
+        if attr.path.is_ident("transitions") {
+            specs.push(attr.parse_args::<TransitionSpec>()?);
+        } else {
+            attrs.push(attr);
+        }
+    }
+    Ok(specs)
+}
+
+/// Generates the transition table of an `#[account]` type.
+///
+/// `_check_transitions` checks every tracked field against the program-wide
+/// table, `_transition_allowed` checks a single field against an instruction
+/// specific list of edges. Leaving a field unchanged is always allowed.
+/// `_TRANSITION_FIELDS` lists the tracked fields, which the fields named by
+/// an instruction's `#[transitions]` are checked against at compile time.
+pub fn transition_table(item: &ItemStruct, specs: &[TransitionSpec]) -> syn::Result<TokenStream> {
+    if specs.is_empty() {
+        return Ok(quote! {});
+    }
+
+    let Fields::Named(fields) = &item.fields else {
+        return Err(syn::Error::new_spanned(
+            &item.ident,
+            "#[transitions] requires named fields",
+        ));
+    };
+
+    let mut arms = vec![];
+    let mut checks = vec![];
+    for spec in specs {
+        let field = &spec.field;
+        let Some(ty) = fields
+            .named
+            .iter()
+            .find(|f| f.ident.as_ref() == Some(field))
+            .map(|f| &f.ty)
+        else {
+            return Err(syn::Error::new_spanned(field, "unknown field"));
+        };
+
+        let mut variants: Vec<&Ident> = vec![];
+        for t in spec.transitions.iter() {
+            for v in [&t.from, &t.to] {
+                if !variants.contains(&v) {
+                    variants.push(v);
+                }
+            }
+        }
+        let names = variants.iter().map(|v| v.to_string());
+
+        let name = field.to_string();
+        arms.push(quote! {
+            #name => {
+                let variant_of = |v: &#ty| -> &'static str {
+                    #[allow(unreachable_patterns)]
+                    match v {
+                        #(#ty::#variants { .. } => #names,)*
+                        _ => "",
+                    }
+                };
+                let (from, to) = (variant_of(&before.#field), variant_of(&self.#field));
+                core::mem::discriminant(&before.#field) == core::mem::discriminant(&self.#field)
+                    || allowed.iter().any(|(f, t)| *f == from && *t == to)
+            }
+        });
+
+        let allowed = spec.allowed();
+        checks.push(quote! {
+            self._transition_allowed(before, #name, #allowed)
+        });
+    }
+
+    let ident = &item.ident;
+    let tracked = specs.iter().map(|spec| spec.field.to_string());
+    Ok(quote! {
+        impl #ident {
+            pub const _TRANSITION_FIELDS: &'static [&'static str] = &[#(#tracked),*];
+
+            pub fn _check_transitions(&self, before: &Self) -> bool {
+                #(#checks)&&*
+            }
+
+            pub fn _transition_allowed(
+                &self,
+                before: &Self,
+                field: &str,
+                allowed: &[(&str, &str)],
+            ) -> bool {
+                match field {
+                    #(#arms)*
+                    _ => true,
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_transition_spec() {
+        let spec: TransitionSpec = syn::parse_quote!(status: Open -> Closed, Open -> Paused);
+        assert_eq!(spec.field, "status");
+        assert_eq!(spec.transitions.len(), 2);
+        assert_eq!(spec.transitions[1].from, "Open");
+        assert_eq!(spec.transitions[1].to, "Paused");
+
+        let expected = quote! { &[("Open", "Closed"), ("Open", "Paused")] };
+        assert_eq!(spec.allowed().to_string(), expected.to_string());
+    }
+}
@@ -24,24 +24,43 @@ pub fn unpackable(arg: TokenStream) -> Result<TokenStream> {
         let t_upper_next = syn::Ident::new(&format!("{}_NEXT", t_upper), Span::call_site());
         let t_upper_next_pack =
             syn::Ident::new(&format!("{}_NEXT_PACK", t_upper), Span::call_site());
+        let t_upper_unchecked =
+            syn::Ident::new(&format!("{}_UNCHECKED", t_upper), Span::call_site());
+        let t_upper_origins = syn::Ident::new(&format!("{}_ORIGINS", t_upper), Span::call_site());
+        let t_upper_packed_from =
+            syn::Ident::new(&format!("{}_PACKED_FROM", t_upper), Span::call_site());
         let harness = quote! {
             static mut #t_upper_plural: Option<Vec<#ty>> = None;
             static mut #t_upper_next: usize = 0;
             static mut #t_upper_next_pack: usize = 0;
+            static mut #t_upper_unchecked: Option<Vec<bool>> = None;
+            // The account each unpack slot was unpacked from, and the
+            // unpack slot each packed value replaces.
+            static mut #t_upper_origins: Option<Vec<usize>> = None;
+            static mut #t_upper_packed_from: Option<Vec<Option<usize>>> = None;
             impl Verify for #ty {
-                fn get_next() -> Self {
+                fn get_next(origin: usize) -> Self {
                     unsafe {
                         assert!(#t_upper_next < #t_upper_plural.as_ref().unwrap().len());
                         let result = #t_upper_plural.as_ref().unwrap()[#t_upper_next];
+                        #t_upper_origins.as_mut().unwrap()[#t_upper_next] = origin;
                         #t_upper_next += 1;
                         result
                     }
                 }
+                fn get_next_unchecked(origin: usize) -> Self {
+                    unsafe {
+                        #t_upper_unchecked.as_mut().unwrap()[#t_upper_next] = true;
+                    }
+                    Self::get_next(origin)
+                }
                 // TODO: We could instead just offer a way to access
                 // a mut slice of the global
                 fn expect_unpack(x: Self) {
                     unsafe {
                         #t_upper_plural.as_mut().unwrap().push(x);
+                        #t_upper_unchecked.as_mut().unwrap().push(false);
+                        #t_upper_origins.as_mut().unwrap().push(0);
                     }
                 }
                 fn num_used() -> usize {
@@ -49,8 +68,11 @@ pub fn unpackable(arg: TokenStream) -> Result<TokenStream> {
                         #t_upper_next
                     }
                 }
-                fn repack(x: Self) {
+                fn repack(x: Self, origin: usize) {
                     unsafe {
+                        let origins = #t_upper_origins.as_ref().unwrap();
+                        let from = (0..#t_upper_next).rev().find(|&i| origins[i] == origin);
+                        #t_upper_packed_from.as_mut().unwrap().push(from);
                         #t_upper_plural.as_mut().unwrap()[#t_upper_next_pack] = x;
                         #t_upper_next_pack += 1;
                     }
@@ -62,6 +84,21 @@ pub fn unpackable(arg: TokenStream) -> Result<TokenStream> {
                         result
                     }
                 }
+                fn num_packed() -> usize {
+                    unsafe {
+                        #t_upper_next_pack
+                    }
+                }
+                fn was_unchecked(i: usize) -> bool {
+                    unsafe {
+                        #t_upper_unchecked.as_ref().unwrap()[i]
+                    }
+                }
+                fn packed_from(i: usize) -> Option<usize> {
+                    unsafe {
+                        #t_upper_packed_from.as_ref().unwrap()[i]
+                    }
+                }
             }
         };
         let init = quote! {
@@ -69,6 +106,9 @@ pub fn unpackable(arg: TokenStream) -> Result<TokenStream> {
                 #t_upper_plural = Some(Vec::new());
                 #t_upper_next = 0;
                 #t_upper_next_pack = 0;
+                #t_upper_unchecked = Some(Vec::new());
+                #t_upper_origins = Some(Vec::new());
+                #t_upper_packed_from = Some(Vec::new());
             }
         };
         harnesses.push(harness);
@@ -12,6 +12,8 @@ use syn::{
     ExprWhile, ExprYield, FnArg, ItemFn, Pat, PatIdent, PatType, Signature, Stmt, Type,
 };
 
+use crate::loop_invariant::expand_loop_invariants;
+
 const KANI_UNWIND_AMOUNT: usize = 16;
 const MAX_UNPACK: usize = 10;
 
@@ -76,23 +78,14 @@ fn remove_verify_ignore_statements(item: &mut ItemFn) {
     }
 }
 
-fn create_succeeds_if(
-    function_sig: &Signature,
+/// Declares the `MAX_UNPACK` values of each unpackable type the harness
+/// hands out to `unpack`, and the statements registering them once `init`
+/// ran. Types for which `keep_before` holds (given the lowercased type name)
+/// also get a `before_<type>s` copy to compare the packed values with.
+fn create_unpack_decls(
     unpack_types: &[(syn::Type, String)],
-    attr: Attribute,
-    parameters: &[PatType],
-    parameter_names: &[Expr],
-) -> syn::Result<TokenStream> {
-    // get precondition that was part of the macro invocation
-    let precondition = match attr.parse_args::<Expr>() {
-        Ok(p) => p.to_token_stream(),
-        Err(_) => quote! { true },
-    };
-
-    let function_name = function_sig.ident.clone();
-    let proof_name = format_ident!("succeeds_if_{}", function_name, span = function_name.span());
-
-    // create all the accounts that can be constrained
+    keep_before: impl Fn(&str) -> bool,
+) -> (Vec<TokenStream>, Vec<TokenStream>) {
     let mut unpack_decls = Vec::new();
     let mut expect_unpack = Vec::new();
     for (t, s) in unpack_types {
@@ -112,7 +105,39 @@ fn create_succeeds_if(
                 #t::expect_unpack(x.clone());
             }
         });
+
+        if keep_before(&t_lower.to_string()) {
+            let before_t_lower_plural =
+                syn::Ident::new(&format!("before_{}s", t_lower), Span::call_site());
+            unpack_decls.push(quote! {
+                let mut #before_t_lower_plural: Vec<#t> = Vec::new();
+                for x in #t_lower_plural.iter() {
+                    #before_t_lower_plural.push(x.clone());
+                }
+            });
+        }
     }
+    (unpack_decls, expect_unpack)
+}
+
+fn create_succeeds_if(
+    function_sig: &Signature,
+    unpack_types: &[(syn::Type, String)],
+    attr: Attribute,
+    parameters: &[PatType],
+    parameter_names: &[Expr],
+) -> syn::Result<TokenStream> {
+    // get precondition that was part of the macro invocation
+    let precondition = match attr.parse_args::<Expr>() {
+        Ok(p) => p.to_token_stream(),
+        Err(_) => quote! { true },
+    };
+
+    let function_name = function_sig.ident.clone();
+    let proof_name = format_ident!("succeeds_if_{}", function_name, span = function_name.span());
+
+    // create all the accounts that can be constrained
+    let (unpack_decls, expect_unpack) = create_unpack_decls(unpack_types, |_| false);
 
     let fn_call = if function_sig.receiver().is_some() {
         quote! {
@@ -190,37 +215,7 @@ fn create_verify(
     let proof_name = format_ident!("verify_{}", function_name, span = function_name.span());
 
     // create all the accounts that can be constrained
-    let mut unpack_decls = Vec::new();
-    let mut expect_unpack = Vec::new();
-    for (t, s) in unpack_types {
-        let t_lower = syn::Ident::new(&s.to_lowercase(), Span::call_site());
-        let t_lower_plural = syn::Ident::new(&format!("{}s", t_lower), Span::call_site());
-        unpack_decls.push(quote! {
-            let mut #t_lower_plural: Vec<#t> = Vec::new();
-            for _ in 0..#MAX_UNPACK {
-                let x = kani::any();
-                #t_lower_plural.push(x);
-                kani::assume(x._check_invariant());
-            }
-        });
-
-        expect_unpack.push(quote! {
-            for x in #t_lower_plural.iter() {
-                #t::expect_unpack(x.clone());
-            }
-        });
-
-        if t_lower == "account" {
-            let before_t_lower_plural =
-                syn::Ident::new(&format!("before_{}s", t_lower), Span::call_site());
-            unpack_decls.push(quote! {
-                let mut #before_t_lower_plural: Vec<#t> = Vec::new();
-                for x in #t_lower_plural.iter() {
-                    #before_t_lower_plural.push(x.clone());
-                }
-            });
-        }
-    }
+    let (unpack_decls, expect_unpack) = create_unpack_decls(unpack_types, |t| t == "account");
 
     let fn_call = if function_sig.receiver().is_some() {
         quote! {
@@ -263,6 +258,76 @@ fn create_verify(
     })
 }
 
+/// Creates the harness reporting instructions that initialize an account
+/// that already was, the classic reinitialization bug of native programs. An
+/// account unpacked with `unpack_unchecked` is being initialized, so the
+/// instruction has to fail if it was initialized before.
+fn create_reinit_check(
+    function_sig: &Signature,
+    unpack_types: &[(syn::Type, String)],
+    parameters: &[PatType],
+    parameter_names: &[Expr],
+) -> syn::Result<TokenStream> {
+    let function_name = function_sig.ident.clone();
+    let proof_name = format_ident!("reinit_{}", function_name, span = function_name.span());
+
+    let (unpack_decls, expect_unpack) = create_unpack_decls(unpack_types, |_| true);
+    let mut reinit_checks = Vec::new();
+    for (t, s) in unpack_types {
+        let before_t_lower_plural =
+            syn::Ident::new(&format!("before_{}s", s.to_lowercase()), Span::call_site());
+        let message =
+            format!("Reinitialization: instruction initialized an initialized `{s}` again");
+        // Packed values are numbered in pack order, the snapshots and the
+        // unchecked flags in unpack order, so each packed value is compared
+        // with the slot unpacked from the account it was packed into.
+        reinit_checks.push(quote! {
+            for i in 0..#t::num_packed() {
+                if let Some(slot) = #t::packed_from(i) {
+                    kani::assert(
+                        !(#t::was_unchecked(slot)
+                            && #before_t_lower_plural[slot].is_initialized()
+                            && #t::get_packed(i).is_initialized()),
+                        #message,
+                    );
+                }
+            }
+        });
+    }
+
+    let fn_call = if function_sig.receiver().is_some() {
+        quote! {
+            let result = #function_name(#(#parameter_names),*);
+        }
+    } else {
+        quote! {
+            let result = Self::#function_name(#(#parameter_names),*);
+        }
+    };
+
+    Ok(quote! {
+        #[kani::proof]
+        #[kani::unwind(#KANI_UNWIND_AMOUNT)]
+        pub fn #proof_name() {
+            #(
+                let #parameters = kani::any();
+            );*
+
+            #(#unpack_decls)*
+
+            init();
+
+            #(#expect_unpack)*
+
+            #fn_call
+
+            if result.is_ok() {
+                #(#reinit_checks)*
+            }
+        }
+    })
+}
+
 fn verification_harness_of(
     item: &mut ItemFn,
     types: &[(syn::Type, String)],
@@ -367,6 +432,12 @@ fn verification_harness_of(
         postcondition,
     )?;
 
+    let reinit = create_reinit_check(function_sig, types, &parameters, &parameter_names)?;
+    let verify = quote! {
+        #verify
+        #reinit
+    };
+
     let res = match precondition {
         Some(precondition) => quote! {
             #verify
@@ -394,6 +465,8 @@ pub fn verify(args: TokenStream, input: TokenStream) -> Result<TokenStream> {
         })
         .collect::<Vec<_>>();
 
+    expand_loop_invariants(&mut item);
+
     let mut harnesses = Vec::new();
     if let Ok(harness) = verification_harness_of(&mut item, &types) {
         println!("created harness for: {:?}", item.sig.ident);
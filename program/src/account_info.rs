@@ -2,7 +2,7 @@ use std::cell::{BorrowError, BorrowMutError};
 
 use super::pubkey::Pubkey;
 use crate::instruction::AccountMeta;
-use crate::stupid_refcell::{StupidRefCell, StupidRefMut};
+use crate::stupid_refcell::{BorrowFlag, StupidRef, StupidRefCell, StupidRefMut};
 use crate::{pubkey::KEYS, vec::sparse::Vec, Key, Result};
 
 #[cfg(not(feature = "verify"))]
@@ -10,26 +10,81 @@ use crate::error::Error;
 
 #[cfg(any(kani, feature = "kani"))]
 use crate::pubkey::kani_new_pubkey;
+#[cfg(any(kani, feature = "kani"))]
+use crate::{program_error::ProgramError, sysvar::Sysvar};
 
+/// An account passed to the program. Like the `Rc`s of solana's, the
+/// lamports, data, owner and discriminator are behind references, so the
+/// copies of an info share the state of the account and its borrows. Each
+/// account needs storage of its own for them, as `new` and `kani::any()`
+/// give it.
 #[derive(Clone, Debug, Copy)]
 pub struct AccountInfo<'a> {
     pub key: &'a Pubkey,
     pub is_signer: bool,
     pub is_writable: bool,
-    pub lamports: StupidRefCell<u64>,
-    pub data: Vec<u8>,
+    pub lamports: &'a StupidRefCell<u64>,
+    pub data: &'a Vec<u8>,
     pub owner: &'a Pubkey,
     pub executable: bool,
     pub rent_epoch: bool, //Epoch,
+    /// The discriminator the data starts with. The model only tracks the
+    /// length of the data, so it holds this tag on the side.
+    pub discriminator: &'a [u8; 8],
+    /// The borrows of `data`, see `BorrowFlag`, shared like the data.
+    pub data_borrows: &'a BorrowFlag,
+}
+
+/// Storage for the state of one account, which lives as long as the harness.
+fn leak<T>(x: T) -> &'static T {
+    Box::leak(Box::new(x))
 }
 
 impl<'a> AccountInfo<'a> {
-    pub fn try_borrow_data(&self) -> Result<&Vec<u8>> {
-        Ok(&self.data)
+    /// An account of `key` with storage of its own for its state.
+    pub fn new(
+        key: &'a Pubkey,
+        is_signer: bool,
+        is_writable: bool,
+        lamports: u64,
+        data_len: usize,
+        owner: Pubkey,
+    ) -> Self {
+        Self {
+            key,
+            is_signer,
+            is_writable,
+            lamports: leak(StupidRefCell::new(lamports)),
+            data: leak(Vec::new_with_size(data_len)),
+            owner: leak(owner),
+            executable: false,
+            rent_epoch: false,
+            discriminator: leak([0; 8]),
+            data_borrows: leak(BorrowFlag::default()),
+        }
     }
 
-    pub fn try_borrow_mut_data(&self) -> Result<&Vec<u8>> {
-        self.try_borrow_data()
+    /// A copy of the account as it is now, with storage of its own that the
+    /// changes to the account don't reach.
+    pub fn detached(&self) -> Self {
+        Self {
+            lamports: leak(StupidRefCell::new(self.lamports())),
+            data: leak(*self.data),
+            owner: leak(*self.owner),
+            discriminator: leak(*self.discriminator),
+            data_borrows: leak(BorrowFlag::default()),
+            ..*self
+        }
+    }
+
+    pub fn try_borrow_data(&self) -> Result<StupidRef<'_, Vec<u8>>> {
+        let guard = self.data_borrows.try_borrow()?;
+        Ok(StupidRef::new(self.data, guard))
+    }
+
+    pub fn try_borrow_mut_data(&self) -> Result<StupidRefMut<'_, Vec<u8>>> {
+        let guard = self.data_borrows.try_borrow_mut()?;
+        Ok(StupidRefMut::new(self.data, guard))
     }
 
     pub fn lamports(&self) -> u64 {
@@ -37,7 +92,7 @@ impl<'a> AccountInfo<'a> {
     }
 
     pub fn try_borrow_lamports(&self) -> std::result::Result<u64, BorrowError> {
-        self.lamports.try_borrow().copied()
+        self.lamports.try_borrow().map(|lamports| *lamports)
     }
 
     pub fn try_borrow_mut_lamports(
@@ -46,7 +101,14 @@ impl<'a> AccountInfo<'a> {
         self.lamports.try_borrow_mut()
     }
 
-    pub fn realloc(&self, _new_len: usize, _zero_init: bool) -> Result<()> {
+    #[allow(invalid_reference_casting)]
+    pub fn realloc(&self, new_len: usize, _zero_init: bool) -> Result<()> {
+        unsafe {
+            std::ptr::write_volatile(
+                self.data as *const Vec<u8> as *mut Vec<u8>,
+                Vec::new_with_size(new_len),
+            );
+        }
         Ok(())
     }
 
@@ -62,15 +124,41 @@ impl<'a> AccountInfo<'a> {
         }
     }
 
+    /// Changes the owner of the account, and of every copy of its info.
     #[allow(invalid_reference_casting)]
     pub fn assign(&self, new_owner: &Pubkey) {
+        unsafe {
+            std::ptr::write_volatile(self.owner as *const Pubkey as *mut Pubkey, *new_owner);
+        }
+    }
+
+    /// Sets the discriminator the data starts with, of every copy of the
+    /// info.
+    #[allow(invalid_reference_casting)]
+    pub fn set_discriminator(&self, discriminator: [u8; 8]) {
         unsafe {
             std::ptr::write_volatile(
-                self.owner as *const Pubkey as *mut [u8; 1],
-                new_owner.to_bytes(),
+                self.discriminator as *const [u8; 8] as *mut [u8; 8],
+                discriminator,
             );
         }
     }
+
+    /// Deserializes the data of the account, like solana's `deserialize_data`
+    /// does with bincode. Only sysvars are modeled. Nothing checks the key of
+    /// the account, so an account other than the sysvar's hands the program
+    /// the bytes of the attacker's choosing.
+    #[cfg(any(kani, feature = "kani"))]
+    pub fn deserialize_data<T: Sysvar + kani::Arbitrary>(
+        &self,
+    ) -> std::result::Result<T, ProgramError> {
+        if T::check_id(self.key) {
+            T::get()
+        } else {
+            crate::sysvar::mark_unchecked_read();
+            Ok(kani::any())
+        }
+    }
 }
 
 impl<'a> AsRef<AccountInfo<'a>> for AccountInfo<'a> {
@@ -109,11 +197,13 @@ impl<'info> kani::Arbitrary for AccountInfo<'info> {
             key: kani_new_pubkey(),
             is_signer: kani::any(),
             is_writable: kani::any(),
-            lamports: kani::any(),
-            data: kani::any(),
+            lamports: leak(kani::any()),
+            data: leak(kani::any()),
             owner: kani_new_pubkey(),
             executable: kani::any(),
             rent_epoch: kani::any(),
+            discriminator: leak(kani::any()),
+            data_borrows: leak(BorrowFlag::default()),
         }
     }
 }
@@ -124,11 +214,13 @@ impl Default for AccountInfo<'_> {
             key: unsafe { KEYS.get(0).unwrap() },
             is_signer: bool::default(),
             is_writable: bool::default(),
-            lamports: Default::default(),
-            data: Vec::<u8>::default(),
-            owner: unsafe { KEYS.get(0).unwrap() },
+            lamports: leak(StupidRefCell::default()),
+            data: leak(Vec::<u8>::default()),
+            owner: leak(Pubkey::default()),
             executable: bool::default(),
             rent_epoch: bool::default(),
+            discriminator: leak([0; 8]),
+            data_borrows: leak(BorrowFlag::default()),
         }
     }
 }
@@ -3,6 +3,7 @@ use crate::error::Error;
 pub type Slot = u64;
 
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(any(kani, feature = "kani"), derive(kani::Arbitrary))]
 pub struct Clock {
     pub unix_timestamp: i64,
 }
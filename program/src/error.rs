@@ -195,7 +195,17 @@ pub enum Error {
     #[error("generic error")]
     Generic,
 
+    // Errors of the programs invoked, e.g. the System program.
+    #[error("A program error occurred: {0:?}")]
+    Program(crate::program_error::ProgramError),
+
     // Custom errors for the program specific errors.
     #[error("An error occurred: {0}")]
     CustomError(String),
 }
+
+impl From<crate::program_error::ProgramError> for Error {
+    fn from(e: crate::program_error::ProgramError) -> Self {
+        Error::Program(e)
+    }
+}
@@ -1,3 +1,5 @@
+use borsh::BorshSerialize;
+
 use super::pubkey::Pubkey;
 #[cfg(any(kani, feature = "kani"))]
 use crate::vec::fast::Vec;
@@ -14,6 +16,25 @@ pub struct Instruction {
     pub data: Vec<u8>,
 }
 
+impl Instruction {
+    pub fn new_with_borsh<T: BorshSerialize>(
+        program_id: Pubkey,
+        data: &T,
+        accounts: Vec<AccountMeta>,
+    ) -> Self {
+        let data = data.try_to_vec().unwrap();
+        Self {
+            program_id,
+            accounts,
+            // The model keeps the bytes in a `Vec` of its own.
+            #[cfg(any(kani, feature = "kani"))]
+            data: Vec::from(data),
+            #[cfg(not(any(kani, feature = "kani")))]
+            data,
+        }
+    }
+}
+
 #[derive(Default, PartialEq, Eq, Clone, Copy)]
 pub struct AccountMeta {
     pub pubkey: Pubkey,
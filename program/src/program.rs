@@ -1,4 +1,10 @@
-use super::{account_info::AccountInfo, instruction::Instruction};
+use super::{
+    account_info::AccountInfo,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    runtime, system_program,
+};
 
 #[cfg(not(feature = "verify"))]
 pub type ProgramResult = Result<(), crate::error::Error>;
@@ -6,35 +12,324 @@ pub type ProgramResult = Result<(), crate::error::Error>;
 #[cfg(feature = "verify")]
 pub type ProgramResult = Result<(), crate::program_error::ProgramError>;
 
+const MAX_KNOWN_PROGRAMS: usize = 16;
+static mut KNOWN_PROGRAMS: [Pubkey; MAX_KNOWN_PROGRAMS] = [Pubkey { t: [0] }; MAX_KNOWN_PROGRAMS];
+static mut KNOWN_PROGRAMS_LEN: usize = 0;
+
+/// Registers `id` as a program the verified program may invoke, e.g. from a
+/// `verify_setup` hook. The system program is always known.
+pub fn add_known_program(id: Pubkey) {
+    unsafe {
+        assert!(
+            KNOWN_PROGRAMS_LEN < MAX_KNOWN_PROGRAMS,
+            "Ran out of known programs."
+        );
+        KNOWN_PROGRAMS[KNOWN_PROGRAMS_LEN] = id;
+        KNOWN_PROGRAMS_LEN += 1;
+    }
+}
+
+pub fn is_known_program(id: &Pubkey) -> bool {
+    system_program::check_id(id)
+        || cpi_model(id).is_some()
+        || unsafe { (0..KNOWN_PROGRAMS_LEN).any(|i| KNOWN_PROGRAMS[i] == *id) }
+}
+
+/// A model of a program the verified program invokes. `invoke` and
+/// `invoke_signed` run the model registered for the invoked program id.
+pub trait CpiModel {
+    /// Runs `instruction`, updating the lamports and data of
+    /// `account_infos` as the program would.
+    fn process(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult;
+}
+
+const MAX_CPI_MODELS: usize = 16;
+static mut CPI_MODEL_IDS: [Pubkey; MAX_CPI_MODELS] = [Pubkey { t: [0] }; MAX_CPI_MODELS];
+static mut CPI_MODELS: [Option<&'static dyn CpiModel>; MAX_CPI_MODELS] = [None; MAX_CPI_MODELS];
+static mut CPI_MODELS_LEN: usize = 0;
+
+/// Registers `model` as the program with id `program_id`, e.g. from a
+/// `verify_setup` hook. Programs with a model are known programs.
+pub fn register_cpi_model(program_id: Pubkey, model: &'static dyn CpiModel) {
+    unsafe {
+        assert!(CPI_MODELS_LEN < MAX_CPI_MODELS, "Ran out of CPI models.");
+        CPI_MODEL_IDS[CPI_MODELS_LEN] = program_id;
+        CPI_MODELS[CPI_MODELS_LEN] = Some(model);
+        CPI_MODELS_LEN += 1;
+    }
+}
+
+/// The model registered for `program_id`, the System program having one by
+/// default.
+pub fn cpi_model(program_id: &Pubkey) -> Option<&'static dyn CpiModel> {
+    let registered = unsafe {
+        (0..CPI_MODELS_LEN)
+            .find(|&i| CPI_MODEL_IDS[i] == *program_id)
+            .and_then(|i| CPI_MODELS[i])
+    };
+    if registered.is_none() && system_program::check_id(program_id) {
+        return Some(&system_program::SystemProgram);
+    }
+    registered
+}
+
+/// Checks a CPI goes to one of `known_ids` or a registered program, and
+/// reports an arbitrary CPI finding when the caller could pick the program
+/// instead.
+#[allow(unused_variables)]
+pub fn check_cpi_target(program_id: &Pubkey, known_ids: &[Pubkey], signed: bool) {
+    #[cfg(any(kani, feature = "kani"))]
+    {
+        let known = known_ids.contains(program_id) || is_known_program(program_id);
+        if signed {
+            kani::assert(
+                known,
+                "Arbitrary CPI: signed invocation of a program that may not be a known id",
+            );
+        } else {
+            kani::assert(
+                known,
+                "Arbitrary CPI: invocation of a program that may not be a known id",
+            );
+        }
+    }
+}
+
+/// The most programs on the stack of invocations, the verified program
+/// included, as enforced by the runtime.
+pub const MAX_INVOKE_DEPTH: usize = 5;
+static mut INVOKE_STACK: [Pubkey; MAX_INVOKE_DEPTH] = [Pubkey { t: [0] }; MAX_INVOKE_DEPTH];
+static mut INVOKE_DEPTH: usize = 0;
+
+/// Sets the id of the verified program, at the bottom of the stack of
+/// invocations. Harnesses call it with the program id of the context.
+pub fn set_program_id(id: Pubkey) {
+    unsafe {
+        INVOKE_STACK[0] = id;
+        INVOKE_DEPTH = 1;
+    }
+}
+
+/// The id of the program currently executing, if it was set.
+pub fn current_program_id() -> Option<Pubkey> {
+    unsafe { INVOKE_DEPTH.checked_sub(1).map(|i| INVOKE_STACK[i]) }
+}
+
+/// Checks an invocation of `program_id` would be accepted by the runtime:
+/// the stack of invocations doesn't get too deep, a program only reenters
+/// itself directly, and no account gets privileges the caller doesn't have.
+/// A callee may only see an account as a signer if the caller's account is
+/// one, or if it is a PDA of the caller signed through `signers_seeds`, and
+/// as writable if the caller's account is writable.
+pub fn check_invocation(
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    account_infos: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+) {
+    let caller = current_program_id();
+    unsafe {
+        assert!(
+            INVOKE_DEPTH < MAX_INVOKE_DEPTH,
+            "CPI rejected: maximum invoke depth exceeded"
+        );
+        assert!(
+            caller == Some(*program_id) || !INVOKE_STACK[..INVOKE_DEPTH].contains(program_id),
+            "CPI rejected: reentrancy into a program on the invoke stack"
+        );
+    }
+
+    for meta in metas {
+        let info = account_infos.iter().find(|info| *info.key == meta.pubkey);
+        let Some(info) = info else {
+            panic!("CPI rejected: an instruction account is missing from the account infos");
+        };
+        assert!(
+            !meta.is_writable || info.is_writable,
+            "CPI rejected: privilege escalation of a read-only account to writable"
+        );
+        assert!(
+            !meta.is_signer || info.is_signer || signs_for(caller, &meta.pubkey, signers_seeds),
+            "CPI rejected: privilege escalation of a non-signer account to signer"
+        );
+    }
+}
+
+/// Whether some of `signers_seeds` derive `key` under the calling program.
+fn signs_for(caller: Option<Pubkey>, key: &Pubkey, signers_seeds: &[&[&[u8]]]) -> bool {
+    caller.is_some_and(|caller| {
+        signers_seeds
+            .iter()
+            .any(|seeds| Pubkey::create_program_address(seeds, &caller) == Some(*key))
+    })
+}
+
 pub fn invoke(instruction: &Instruction, account_infos: &[AccountInfo]) -> ProgramResult {
     invoke_signed(instruction, account_infos, &[])
 }
 
 pub fn invoke_signed(
-    _instruction: &Instruction,
-    _account_infos: &[AccountInfo],
-    _signers_seeds: &[&[&[u8]]],
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    invoke_signed_with(
+        instruction,
+        account_infos,
+        signers_seeds,
+        &[],
+        |instruction, account_infos, signers_seeds| {
+            let Some(model) = cpi_model(&instruction.program_id) else {
+                panic!("Unmodeled CPI: no CpiModel is registered for the invoked program");
+            };
+            model.process(instruction, account_infos, signers_seeds)
+        },
+    )
+}
+
+/// Runs a CPI of `instruction` with `process` standing for the invoked
+/// program, checking it as `invoke_signed` does. The CPI helpers go through
+/// it with the programs they are written for as `known_ids`, see
+/// `check_cpi_target`.
+pub fn invoke_signed_with(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+    known_ids: &[Pubkey],
+    process: impl FnOnce(&Instruction, &[AccountInfo], &[&[&[u8]]]) -> ProgramResult,
 ) -> ProgramResult {
-    panic!("Invocation is not supported yet.");
-
-    // Check that the account RefCells are consistent with the request
-    // for account_meta in instruction.accounts.iter() {
-    //     for account_info in account_infos.iter() {
-    //         if account_meta.pubkey == *account_info.key {
-    //             if account_meta.is_writable {
-    //                 let _ = account_info.try_borrow_mut_lamports()?;
-    //                 let _ = account_info.try_borrow_mut_data()?;
-    //             } else {
-    //                 let _ = account_info.try_borrow_lamports()?;
-    //                 let _ = account_info.try_borrow_data()?;
-    //             }
-    //             break;
-    //         }
-    //     }
-    // }
-    // Ok(())
+    check_cpi_target(
+        &instruction.program_id,
+        known_ids,
+        !signers_seeds.is_empty(),
+    );
+    check_invocation(
+        &instruction.program_id,
+        &instruction.accounts,
+        account_infos,
+        signers_seeds,
+    );
+
+    // Like the runtime, check the accounts can be borrowed the way the
+    // callee uses them, which only fails with `borrow-tracking`.
+    for meta in instruction.accounts.iter() {
+        let Some(info) = account_infos.iter().find(|info| *info.key == meta.pubkey) else {
+            continue;
+        };
+        let borrowable = if meta.is_writable {
+            info.lamports.try_borrow_mut().is_ok() && info.try_borrow_mut_data().is_ok()
+        } else {
+            info.lamports.try_borrow().is_ok() && info.try_borrow_data().is_ok()
+        };
+        if !borrowable {
+            return Err(ProgramError::AccountBorrowFailed.into());
+        }
+    }
+
+    // The caller's changes so far are its own, and the callee's are checked
+    // against the accounts it owns when it returns.
+    runtime::sync_accounts(account_infos, current_program_id().as_ref());
+    unsafe {
+        INVOKE_STACK[INVOKE_DEPTH] = instruction.program_id;
+        INVOKE_DEPTH += 1;
+    }
+    let result = process(instruction, account_infos, signers_seeds);
+    unsafe {
+        INVOKE_DEPTH -= 1;
+    }
+    runtime::sync_accounts(account_infos, Some(&instruction.program_id));
+    result
 }
 
 pub fn set_return_data(_data: &[u8]) {
     panic!("not implemented");
 }
+
+/// Starts a test with empty registries of programs, invocations and synced
+/// accounts. They are `static mut`s, so the tests using them hold the guard
+/// to run one at a time.
+#[cfg(test)]
+pub(crate) fn isolate() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let guard = LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    unsafe {
+        KNOWN_PROGRAMS_LEN = 0;
+        CPI_MODELS_LEN = 0;
+        INVOKE_DEPTH = 0;
+    }
+    runtime::begin_instruction(&[]);
+    guard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Airdrop;
+
+    impl CpiModel for Airdrop {
+        fn process(
+            &self,
+            _instruction: &Instruction,
+            account_infos: &[AccountInfo],
+            _signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            **account_infos[0].lamports.borrow_mut() += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_invoke_model() {
+        let _registries = isolate();
+        let program_id = Pubkey { t: [7] };
+        register_cpi_model(program_id, &Airdrop);
+
+        let infos = [AccountInfo::default()];
+        let instruction = Instruction {
+            program_id,
+            ..Default::default()
+        };
+        assert!(invoke(&instruction, &infos).is_ok());
+        assert_eq!(infos[0].lamports(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "borrow-tracking")]
+    fn test_invoke_while_borrowed() {
+        let _registries = isolate();
+        let program_id = Pubkey { t: [7] };
+        register_cpi_model(program_id, &Airdrop);
+
+        let info = AccountInfo {
+            is_writable: true,
+            ..Default::default()
+        };
+        let instruction = Instruction {
+            program_id,
+            accounts: [info.to_account_meta(false)].into(),
+            ..Default::default()
+        };
+        // The CPI gets a copy of the info, as `to_account_info()` gives.
+        let data = info.try_borrow_mut_data().unwrap();
+        let copy = info;
+        assert!(invoke(&instruction, &[copy]).is_err());
+        drop(data);
+        assert!(invoke(&instruction, &[copy]).is_ok());
+        assert_eq!(info.lamports(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "privilege escalation")]
+    fn test_writable_escalation() {
+        let _registries = isolate();
+        let info = AccountInfo::default();
+        let meta = AccountMeta::new(*info.key, false);
+        check_invocation(&Pubkey { t: [7] }, &[meta], &[info], &[]);
+    }
+}
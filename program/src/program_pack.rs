@@ -13,11 +13,23 @@ pub trait Sealed: Sized {}
 // osec trait
 // TODO: we can write a macro that generates this using a global
 pub trait Verify {
-    fn get_next() -> Self;
+    /// Takes the next expected value, unpacked from the account data at
+    /// `origin` (see `SparseSlice::origin`).
+    fn get_next(origin: usize) -> Self;
+    /// Same as `get_next`, recording the account was unpacked without
+    /// checking it is initialized.
+    fn get_next_unchecked(origin: usize) -> Self;
     fn expect_unpack(x: Self);
-    fn repack(x: Self);
+    /// Records `x` as packed into the account data at `origin`.
+    fn repack(x: Self, origin: usize);
     fn get_packed(i: usize) -> Self;
     fn num_used() -> usize;
+    fn num_packed() -> usize;
+    /// Whether the unpack slot `i` was unpacked with `unpack_unchecked`.
+    fn was_unchecked(i: usize) -> bool;
+    /// The unpack slot of the last value unpacked from the account the
+    /// `i`th packed value was packed into, if any was.
+    fn packed_from(i: usize) -> Option<usize>;
 }
 
 // non-kani version (so you can build without...)
@@ -85,7 +97,7 @@ pub trait Pack: Sealed + Verify {
     where
         Self: IsInitialized,
     {
-        let value = Self::unpack_unchecked(input)?;
+        let value = Self::get_next(input.origin());
         if value.is_initialized() {
             Ok(value)
         } else {
@@ -94,7 +106,7 @@ pub trait Pack: Sealed + Verify {
     }
 
     /// Unpack from slice without checking if initialized
-    fn unpack_unchecked(_input: &SparseSlice<u8>) -> Result<Self, ProgramError> {
+    fn unpack_unchecked(input: &SparseSlice<u8>) -> Result<Self, ProgramError> {
         // if input.len() != Self::LEN {
         //     return Err(ProgramError::InvalidAccountData);
         // }
@@ -106,7 +118,7 @@ pub trait Pack: Sealed + Verify {
         // XXX: This is a temporary fix because we are currently
         // just asserting that the right number of accounts
         // will be deserialized at the end.
-        Ok(Self::get_next())
+        Ok(Self::get_next_unchecked(input.origin()))
         // } else {
         //     Err(ProgramError::InvalidAccountData)
         // }
@@ -114,11 +126,9 @@ pub trait Pack: Sealed + Verify {
     }
 
     /// Pack into slice
-    fn pack(src: Self, _dst: &mut SparseSlice<u8>) -> Result<(), ProgramError> {
+    fn pack(src: Self, dst: &mut SparseSlice<u8>) -> Result<(), ProgramError> {
         // TODO: We should make sure all the accounts get re-serialized
-        // TODO: Need to handle cases where accounts are repacked in
-        // a different order
-        Self::repack(src);
+        Self::repack(src, dst.origin());
         Ok(())
     }
 }
@@ -2,7 +2,11 @@ use std::hash::Hash;
 
 use borsh::{BorshDeserialize, BorshSerialize};
 
+use crate::program_error::ProgramError;
+
 pub const PUBKEY_BYTES: usize = 1;
+/// The longest seed of `create_with_seed`.
+pub const MAX_SEED_LEN: usize = 32;
 
 #[derive(
     PartialEq,
@@ -42,8 +46,8 @@ impl Pubkey {
     }
 
     #[cfg(any(kani, feature = "kani"))]
-    pub fn create_program_address(_seeds: &[&[u8]], _program_id: &Pubkey) -> Option<Pubkey> {
-        Some(kani::any())
+    pub fn create_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> Option<Pubkey> {
+        pda::create_program_address(seeds, program_id)
     }
 }
 
@@ -54,6 +58,28 @@ impl Pubkey {
     }
 }
 
+impl Pubkey {
+    /// The address of the account of `owner` derived from `base` and `seed`.
+    /// The model gives it an arbitrary address per base, seed and owner.
+    pub fn create_with_seed(
+        base: &Pubkey,
+        seed: &str,
+        owner: &Pubkey,
+    ) -> Result<Pubkey, ProgramError> {
+        if seed.len() > MAX_SEED_LEN {
+            return Err(ProgramError::MaxSeedLengthExceeded);
+        }
+        #[cfg(any(kani, feature = "kani"))]
+        let address = seeded::create_with_seed(base, seed.as_bytes(), owner);
+        #[cfg(not(any(kani, feature = "kani")))]
+        let address = {
+            let _ = (base, owner);
+            Pubkey::default()
+        };
+        Ok(address)
+    }
+}
+
 impl Default for &Pubkey {
     fn default() -> Self {
         &Pubkey {
@@ -64,8 +90,200 @@ impl Default for &Pubkey {
 
 #[cfg(any(kani, feature = "kani"))]
 impl Pubkey {
-    pub fn find_program_address(_seeds: &[&[u8]], _program_id: &Pubkey) -> (Pubkey, u8) {
-        (kani::any(), kani::any())
+    pub fn find_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> (Pubkey, u8) {
+        pda::find_program_address(seeds, program_id)
+    }
+}
+
+#[cfg(any(kani, feature = "kani"))]
+pub use pda::non_canonical_bump_used;
+
+/// Model of program derived addresses. Every set of seeds gets an arbitrary
+/// canonical bump and address the first time it is used, which later uses
+/// agree on. Bumps above the canonical one are invalid, and the ones below
+/// it may each be valid and derive an address of their own.
+#[cfg(any(kani, feature = "kani"))]
+mod pda {
+    use std::cmp::Ordering;
+
+    use super::Pubkey;
+
+    const MAX_SEEDS_LEN: usize = 64;
+    const MAX_PDAS: usize = 16;
+
+    /// Seeds are concatenated before being hashed, so only their bytes matter.
+    #[derive(Clone, Copy, PartialEq)]
+    struct Seeds {
+        bytes: [u8; MAX_SEEDS_LEN],
+        len: usize,
+        program_id: Pubkey,
+    }
+
+    impl Seeds {
+        fn new(seeds: &[&[u8]], program_id: &Pubkey) -> Self {
+            let mut bytes = [0; MAX_SEEDS_LEN];
+            let mut len = 0;
+            for seed in seeds {
+                for byte in seed.iter() {
+                    kani::assert(len < MAX_SEEDS_LEN, "Seeds too long for the PDA model.");
+                    bytes[len] = *byte;
+                    len += 1;
+                }
+            }
+            Self {
+                bytes,
+                len,
+                program_id: *program_id,
+            }
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct Pda {
+        seeds: Seeds,
+        canonical_bump: u8,
+        address: Pubkey,
+    }
+
+    /// The outcome of deriving an address with a non-canonical bump, or
+    /// without a bump when `bump` is `None`.
+    #[derive(Clone, Copy)]
+    struct NonCanonical {
+        pda: usize,
+        bump: Option<u8>,
+        address: Option<Pubkey>,
+    }
+
+    static mut PDAS: [Option<Pda>; MAX_PDAS] = [None; MAX_PDAS];
+    static mut PDAS_LEN: usize = 0;
+    static mut NON_CANONICAL: [Option<NonCanonical>; MAX_PDAS] = [None; MAX_PDAS];
+    static mut NON_CANONICAL_LEN: usize = 0;
+    static mut NON_CANONICAL_BUMP_USED: bool = false;
+
+    /// The index of the PDA of `seeds`, added if they weren't used before.
+    fn pda(seeds: Seeds) -> usize {
+        unsafe {
+            if let Some(i) = (0..PDAS_LEN).find(|&i| PDAS[i].unwrap().seeds == seeds) {
+                return i;
+            }
+            kani::assert(PDAS_LEN < MAX_PDAS, "Ran out of PDAs.");
+            PDAS[PDAS_LEN] = Some(Pda {
+                seeds,
+                canonical_bump: kani::any(),
+                address: kani::any(),
+            });
+            PDAS_LEN += 1;
+            PDAS_LEN - 1
+        }
+    }
+
+    /// Addresses derived from other bumps than the canonical one, or without
+    /// one, are arbitrary but never the canonical address.
+    fn non_canonical(pda: usize, bump: Option<u8>) -> Option<Pubkey> {
+        unsafe {
+            if let Some(derived) = (0..NON_CANONICAL_LEN)
+                .map(|i| NON_CANONICAL[i].unwrap())
+                .find(|derived| derived.pda == pda && derived.bump == bump)
+            {
+                return derived.address;
+            }
+            kani::assert(NON_CANONICAL_LEN < MAX_PDAS, "Ran out of PDAs.");
+            let address = if kani::any() {
+                let address: Pubkey = kani::any();
+                kani::assume(address != PDAS[pda].unwrap().address);
+                Some(address)
+            } else {
+                None
+            };
+            NON_CANONICAL[NON_CANONICAL_LEN] = Some(NonCanonical { pda, bump, address });
+            NON_CANONICAL_LEN += 1;
+            address
+        }
+    }
+
+    pub fn find_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> (Pubkey, u8) {
+        let pda = unsafe { PDAS[pda(Seeds::new(seeds, program_id))].unwrap() };
+        (pda.address, pda.canonical_bump)
+    }
+
+    /// The last seed is taken to be the bump when it is a single byte.
+    /// Otherwise the seeds derive an address other than their canonical one.
+    pub fn create_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> Option<Pubkey> {
+        let Some(([bump], without_bump)) = seeds.split_last().map(|(b, s)| (*b, s)) else {
+            return non_canonical(pda(Seeds::new(seeds, program_id)), None);
+        };
+
+        let index = pda(Seeds::new(without_bump, program_id));
+        let pda = unsafe { PDAS[index].unwrap() };
+        match bump.cmp(&pda.canonical_bump) {
+            Ordering::Equal => Some(pda.address),
+            Ordering::Greater => None,
+            Ordering::Less => {
+                let address = non_canonical(index, Some(*bump));
+                if address.is_some() {
+                    unsafe { NON_CANONICAL_BUMP_USED = true };
+                }
+                address
+            }
+        }
+    }
+
+    /// Whether an address was derived with a valid bump that isn't the
+    /// canonical one.
+    pub fn non_canonical_bump_used() -> bool {
+        unsafe { NON_CANONICAL_BUMP_USED }
+    }
+}
+
+/// Model of addresses derived with `create_with_seed`. Every base, seed and
+/// owner get an arbitrary address the first time they are used, which later
+/// uses agree on. They are kept apart from the PDAs: solana hashes them
+/// differently, so the same bytes needn't derive the same address.
+#[cfg(any(kani, feature = "kani"))]
+mod seeded {
+    use super::{Pubkey, MAX_SEED_LEN};
+
+    const MAX_SEEDED: usize = 16;
+
+    #[derive(Clone, Copy, PartialEq)]
+    struct Seed {
+        base: Pubkey,
+        bytes: [u8; MAX_SEED_LEN],
+        len: usize,
+        owner: Pubkey,
+    }
+
+    #[derive(Clone, Copy)]
+    struct Seeded {
+        seed: Seed,
+        address: Pubkey,
+    }
+
+    static mut SEEDED: [Option<Seeded>; MAX_SEEDED] = [None; MAX_SEEDED];
+    static mut SEEDED_LEN: usize = 0;
+
+    pub fn create_with_seed(base: &Pubkey, seed: &[u8], owner: &Pubkey) -> Pubkey {
+        let mut bytes = [0; MAX_SEED_LEN];
+        bytes[..seed.len()].copy_from_slice(seed);
+        let seed = Seed {
+            base: *base,
+            bytes,
+            len: seed.len(),
+            owner: *owner,
+        };
+        unsafe {
+            if let Some(seeded) = (0..SEEDED_LEN)
+                .map(|i| SEEDED[i].unwrap())
+                .find(|seeded| seeded.seed == seed)
+            {
+                return seeded.address;
+            }
+            kani::assert(SEEDED_LEN < MAX_SEEDED, "Ran out of seeded addresses.");
+            let address = kani::any();
+            SEEDED[SEEDED_LEN] = Some(Seeded { seed, address });
+            SEEDED_LEN += 1;
+            address
+        }
     }
 }
 
@@ -99,3 +317,20 @@ pub fn kani_new_pubkey() -> &'static Pubkey {
         KEYS.get(KEYS_IDX - 1).unwrap()
     }
 }
+
+/// Whether the accounts of a context may share keys. Every account gets a key
+/// of its own, so accounts with the same key are ruled out, unless aliasing
+/// is enabled, where they are the same account, passed twice.
+#[cfg(any(kani, feature = "kani"))]
+static mut ALIASING: bool = false;
+
+/// Enables aliasing for the contexts created afterwards.
+#[cfg(any(kani, feature = "kani"))]
+pub fn set_aliasing(enabled: bool) {
+    unsafe { ALIASING = enabled };
+}
+
+#[cfg(any(kani, feature = "kani"))]
+pub fn aliasing() -> bool {
+    unsafe { ALIASING }
+}
@@ -0,0 +1,216 @@
+//! The rules the runtime checks the accounts against after an instruction,
+//! rejecting the transaction if they are broken.
+
+use super::{account_info::AccountInfo, pubkey::Pubkey, rent::Rent};
+
+/// The state of an account the runtime rules compare. The model only tracks
+/// the length and discriminator of the data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccountSnapshot {
+    pub key: Pubkey,
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub data_len: usize,
+    pub discriminator: [u8; 8],
+    pub is_writable: bool,
+    pub executable: bool,
+}
+
+impl AccountSnapshot {
+    pub fn of(info: &AccountInfo) -> Self {
+        Self {
+            key: *info.key,
+            owner: *info.owner,
+            lamports: info.lamports(),
+            data_len: info.data_len(),
+            discriminator: *info.discriminator,
+            is_writable: info.is_writable,
+            executable: info.executable,
+        }
+    }
+
+    fn data_changed(&self, before: &AccountSnapshot) -> bool {
+        self.data_len != before.data_len || self.discriminator != before.discriminator
+    }
+}
+
+/// The lamports of the accounts, counting accounts passed several times once.
+fn total_lamports(accounts: &[AccountSnapshot]) -> u128 {
+    accounts
+        .iter()
+        .enumerate()
+        .filter(|(i, a)| !accounts[..*i].iter().any(|b| b.key == a.key))
+        .map(|(_, a)| a.lamports as u128)
+        .sum()
+}
+
+const MAX_SYNCED: usize = 32;
+static mut SYNCED: [Option<AccountSnapshot>; MAX_SYNCED] = [None; MAX_SYNCED];
+static mut SYNCED_LEN: usize = 0;
+
+/// The account of `key` as it was when the program now executing got it,
+/// i.e. at the start of the instruction or when its last CPI returned.
+fn synced(key: &Pubkey) -> Option<AccountSnapshot> {
+    unsafe { (0..SYNCED_LEN).find_map(|i| SYNCED[i].filter(|a| a.key == *key)) }
+}
+
+fn sync(account: AccountSnapshot) {
+    unsafe {
+        let i = (0..SYNCED_LEN)
+            .find(|&i| SYNCED[i].is_some_and(|a| a.key == account.key))
+            .unwrap_or(SYNCED_LEN);
+        if i == SYNCED_LEN {
+            assert!(SYNCED_LEN < MAX_SYNCED, "Ran out of synced accounts.");
+            SYNCED_LEN += 1;
+        }
+        SYNCED[i] = Some(account);
+    }
+}
+
+/// Starts an instruction on `accounts`, which the changes of the programs
+/// are compared with.
+pub fn begin_instruction(accounts: &[AccountSnapshot]) {
+    unsafe { SYNCED_LEN = 0 };
+    for account in accounts {
+        sync(*account);
+    }
+}
+
+/// Checks `program_id` only debited, or changed the data or owner of, the
+/// accounts it owned, from `before` to `after`.
+fn verify_ownership(before: &AccountSnapshot, after: &AccountSnapshot, program_id: &Pubkey) {
+    let owns = before.owner == *program_id;
+    assert!(
+        after.lamports >= before.lamports || owns,
+        "Runtime rules: an instruction debited an account its program doesn't own"
+    );
+    assert!(
+        !after.data_changed(before) || owns,
+        "Runtime rules: an instruction modified the data of an account its program doesn't own"
+    );
+    assert!(
+        after.owner == before.owner || owns,
+        "Runtime rules: an instruction changed the owner of an account its program doesn't own"
+    );
+}
+
+/// Checks the changes `program_id` made to `account_infos` since it got them,
+/// and records them as the accounts the next program gets. `invoke*` calls
+/// it for the caller before running the callee, and for the callee after, so
+/// that each program is held to the accounts it owns.
+pub fn sync_accounts(account_infos: &[AccountInfo], program_id: Option<&Pubkey>) {
+    for info in account_infos {
+        let now = AccountSnapshot::of(info);
+        if let (Some(before), Some(program_id)) = (synced(info.key), program_id) {
+            verify_ownership(&before, &now, program_id);
+        }
+        sync(now);
+    }
+}
+
+/// Checks the accounts of a successful instruction of `program_id` went from
+/// `before` to `after` as the runtime allows, the accounts being in the same
+/// order. Only the owner of an account may debit it or change its data or
+/// owner: the changes of the programs it invoked are checked when their CPI
+/// returns, and the rest against `program_id`. Read-only accounts don't
+/// change, `executable` never does, the instruction keeps the total of
+/// lamports, and accounts holding lamports stay rent exempt unless they are
+/// left untouched.
+pub fn verify_runtime_rules(
+    before: &[AccountSnapshot],
+    after: &[AccountSnapshot],
+    program_id: &Pubkey,
+) {
+    assert!(
+        before.len() == after.len(),
+        "Runtime rules: different accounts before and after the instruction"
+    );
+
+    for (b, a) in before.iter().zip(after) {
+        let modified = a.lamports != b.lamports || a.owner != b.owner || a.data_changed(b);
+        assert!(
+            a.executable == b.executable,
+            "Runtime rules: an instruction changed whether an account is executable"
+        );
+        assert!(
+            b.is_writable || !modified,
+            "Runtime rules: an instruction modified a read-only account"
+        );
+        verify_ownership(&synced(&b.key).unwrap_or(*b), a, program_id);
+        assert!(
+            a.lamports == 0 || !modified || Rent::default().is_exempt(a.lamports, a.data_len),
+            "Runtime rules: an instruction left an account that isn't rent exempt"
+        );
+    }
+
+    assert!(
+        total_lamports(before) == total_lamports(after),
+        "Runtime rules: an instruction changed the total of lamports"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        program::{invoke, isolate, set_program_id},
+        system_instruction, system_program,
+    };
+
+    fn account(key: u8, lamports: u64) -> AccountSnapshot {
+        AccountSnapshot {
+            key: Pubkey { t: [key] },
+            owner: Pubkey { t: [7] },
+            lamports,
+            data_len: 0,
+            discriminator: [0; 8],
+            is_writable: true,
+            executable: false,
+        }
+    }
+
+    #[test]
+    fn test_runtime_rules() {
+        let _registries = isolate();
+        let program_id = Pubkey { t: [7] };
+        let before = [account(1, 2_000_000), account(2, 2_000_000)];
+        let after = [account(1, 1_000_000), account(2, 3_000_000)];
+        begin_instruction(&before);
+        verify_runtime_rules(&before, &after, &program_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "total of lamports")]
+    fn test_minted_lamports() {
+        let _registries = isolate();
+        let program_id = Pubkey { t: [7] };
+        let before = [account(1, 2_000_000)];
+        let after = [account(1, 3_000_000)];
+        begin_instruction(&before);
+        verify_runtime_rules(&before, &after, &program_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "debited an account its program doesn't own")]
+    fn test_debit_after_cpi() {
+        let _registries = isolate();
+        let program_id = Pubkey { t: [7] };
+        let (wallet_key, vault_key) = (Pubkey { t: [1] }, Pubkey { t: [2] });
+        let wallet = AccountInfo::new(&wallet_key, true, true, 4_000_000, 0, system_program::ID);
+        let vault = AccountInfo::new(&vault_key, false, true, 2_000_000, 0, program_id);
+        let infos = [wallet, vault];
+        let before = infos.map(|info| AccountSnapshot::of(&info));
+        set_program_id(program_id);
+        begin_instruction(&before);
+
+        let ix = system_instruction::transfer(&wallet_key, &vault_key, 1_000_000);
+        assert!(invoke(&ix, &infos).is_ok());
+        // The System program moved lamports of the wallet, which doesn't let
+        // the program take more of them itself.
+        **wallet.lamports.borrow_mut() -= 1_000_000;
+        **vault.lamports.borrow_mut() += 1_000_000;
+
+        let after = infos.map(|info| AccountSnapshot::of(&info));
+        verify_runtime_rules(&before, &after, &program_id);
+    }
+}
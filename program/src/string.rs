@@ -29,3 +29,13 @@ impl kani::Arbitrary for String {
         Self { vec: kani::any() }
     }
 }
+
+#[cfg(any(kani, feature = "kani"))]
+impl String {
+    /// Generates a string of at most `max_len` arbitrary bytes.
+    pub fn any_bounded(max_len: usize) -> Self {
+        Self {
+            vec: Vec::any_bounded(max_len),
+        }
+    }
+}
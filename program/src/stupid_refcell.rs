@@ -1,18 +1,141 @@
-use std::cell::{BorrowError, BorrowMutError};
+use std::cell::{BorrowError, BorrowMutError, RefCell};
+use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 
+/// The borrows of a value, tracked with the `borrow-tracking` feature like
+/// `RefCell` does, so that overlapping borrows fail as on chain.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BorrowFlag {
+    /// The number of shared borrows, or -1 for a mutable one.
+    #[cfg(feature = "borrow-tracking")]
+    borrows: isize,
+}
+
+/// Releases a borrow of a `BorrowFlag` when dropped.
+pub struct BorrowGuard<'a> {
+    #[cfg(feature = "borrow-tracking")]
+    flag: &'a BorrowFlag,
+    _flag: PhantomData<&'a BorrowFlag>,
+}
+
+fn borrow_error() -> BorrowError {
+    let cell = RefCell::new(());
+    let _guard = cell.borrow_mut();
+    let err = cell.try_borrow().unwrap_err();
+    err
+}
+
+#[cfg(feature = "borrow-tracking")]
+fn borrow_mut_error() -> BorrowMutError {
+    let cell = RefCell::new(());
+    let _guard = cell.borrow();
+    let err = cell.try_borrow_mut().unwrap_err();
+    err
+}
+
+impl BorrowFlag {
+    /// Whether the value is mutably borrowed.
+    #[cfg(feature = "borrow-tracking")]
+    pub fn is_borrowed_mut(&self) -> bool {
+        self.borrows < 0
+    }
+
+    /// Whether the value is mutably borrowed, which isn't tracked.
+    #[cfg(not(feature = "borrow-tracking"))]
+    pub fn is_borrowed_mut(&self) -> bool {
+        false
+    }
+
+    // Like the values of `StupidRefCell`, the flag changes through shared
+    // references.
+    #[cfg(feature = "borrow-tracking")]
+    #[allow(invalid_reference_casting)]
+    fn add(&self, borrows: isize) {
+        unsafe {
+            std::ptr::write_volatile(
+                &self.borrows as *const isize as *mut isize,
+                self.borrows + borrows,
+            );
+        }
+    }
+
+    fn guard(&self) -> BorrowGuard<'_> {
+        BorrowGuard {
+            #[cfg(feature = "borrow-tracking")]
+            flag: self,
+            _flag: PhantomData,
+        }
+    }
+
+    pub fn try_borrow(&self) -> Result<BorrowGuard<'_>, BorrowError> {
+        if self.is_borrowed_mut() {
+            return Err(borrow_error());
+        }
+        #[cfg(feature = "borrow-tracking")]
+        self.add(1);
+        Ok(self.guard())
+    }
+
+    pub fn try_borrow_mut(&self) -> Result<BorrowGuard<'_>, BorrowMutError> {
+        #[cfg(feature = "borrow-tracking")]
+        {
+            if self.borrows != 0 {
+                return Err(borrow_mut_error());
+            }
+            self.add(-1);
+        }
+        Ok(self.guard())
+    }
+}
+
+#[cfg(feature = "borrow-tracking")]
+impl Drop for BorrowGuard<'_> {
+    fn drop(&mut self) {
+        if self.flag.is_borrowed_mut() {
+            self.flag.add(1);
+        } else {
+            self.flag.add(-1);
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct StupidRefCell<T> {
     x: T,
+    flag: BorrowFlag,
 }
 impl<T> StupidRefCell<T> {
     pub fn new(x: T) -> Self {
-        Self { x }
+        Self {
+            x,
+            flag: BorrowFlag::default(),
+        }
+    }
+}
+
+/// A shared borrow of a value whose borrows are tracked by a `BorrowFlag`.
+pub struct StupidRef<'a, T> {
+    x: &'a T,
+    _guard: BorrowGuard<'a>,
+}
+
+impl<'a, T> StupidRef<'a, T> {
+    pub fn new(x: &'a T, guard: BorrowGuard<'a>) -> Self {
+        Self { x, _guard: guard }
+    }
+}
+
+impl<T> Deref for StupidRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.x
     }
 }
 
 pub struct StupidRefMut<'a, T> {
     x: &'a mut T,
+    _guard: BorrowGuard<'a>,
 }
 
 #[allow(clippy::should_implement_trait)]
@@ -20,20 +143,22 @@ impl<T> StupidRefCell<T>
 where
     T: Copy + Clone + std::convert::Into<u64>,
 {
-    pub fn borrow(&self) -> &T {
-        &self.x
+    pub fn borrow(&self) -> StupidRef<'_, T> {
+        self.try_borrow().expect("already mutably borrowed")
     }
 
-    pub fn try_borrow(&self) -> Result<&T, BorrowError> {
-        Ok(self.borrow())
+    pub fn try_borrow(&self) -> Result<StupidRef<'_, T>, BorrowError> {
+        let guard = self.flag.try_borrow()?;
+        Ok(StupidRef::new(&self.x, guard))
     }
 
-    pub fn borrow_mut(&self) -> StupidRefMut<T> {
-        StupidRefMut::new(&self.x)
+    pub fn borrow_mut(&self) -> StupidRefMut<'_, T> {
+        self.try_borrow_mut().expect("already borrowed")
     }
 
-    pub fn try_borrow_mut(&self) -> Result<StupidRefMut<T>, BorrowMutError> {
-        Ok(self.borrow_mut())
+    pub fn try_borrow_mut(&self) -> Result<StupidRefMut<'_, T>, BorrowMutError> {
+        let guard = self.flag.try_borrow_mut()?;
+        Ok(StupidRefMut::new(&self.x, guard))
     }
 }
 
@@ -42,18 +167,16 @@ where
     T: Clone,
 {
     fn clone(&self) -> Self {
-        Self { x: self.x.clone() }
+        Self::new(self.x.clone())
     }
 }
 
-impl<T> Copy for StupidRefCell<T> where T: Copy {}
-
 impl<T> Default for StupidRefCell<T>
 where
     T: Default,
 {
     fn default() -> Self {
-        Self { x: T::default() }
+        Self::new(T::default())
     }
 }
 
@@ -63,16 +186,17 @@ where
     T: kani::Arbitrary,
 {
     fn any() -> Self {
-        Self { x: kani::any() }
+        Self::new(kani::any())
     }
 }
 
 // This is intentionally cursed to accomodate Rc<RefCell<&'a mut u64>> in AccountInfo
 #[allow(invalid_reference_casting)]
-impl<T> StupidRefMut<'_, T> {
-    fn new(x: &T) -> Self {
+impl<'a, T> StupidRefMut<'a, T> {
+    pub fn new(x: &'a T, guard: BorrowGuard<'a>) -> Self {
         Self {
             x: unsafe { &mut *(x as *const T as *mut T) },
+            _guard: guard,
         }
     }
 }
@@ -96,3 +220,28 @@ impl<'a, T> DerefMut for StupidRefMut<'a, T> {
         &mut self.x
     }
 }
+
+#[cfg(all(test, feature = "borrow-tracking"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlapping_borrows() {
+        let lamports = StupidRefCell::new(1u64);
+        let borrowed = lamports.try_borrow_mut().unwrap();
+        assert!(lamports.try_borrow_mut().is_err());
+        assert!(lamports.try_borrow().is_err());
+        drop(borrowed);
+        let shared = lamports.try_borrow().unwrap();
+        assert!(lamports.try_borrow_mut().is_err());
+        drop(shared);
+        assert!(lamports.try_borrow_mut().is_ok());
+
+        let flag = BorrowFlag::default();
+        let shared = flag.try_borrow().unwrap();
+        assert!(flag.try_borrow().is_ok());
+        assert!(flag.try_borrow_mut().is_err());
+        drop(shared);
+        assert!(flag.try_borrow_mut().is_ok());
+    }
+}
@@ -1,5 +1,228 @@
-use super::{instruction::Instruction, pubkey::Pubkey};
+use borsh::{BorshDeserialize, BorshSerialize};
 
-pub fn transfer(_from_pubkey: &Pubkey, _to_pubkey: &Pubkey, _lamports: u64) -> Instruction {
-    todo!();
+use super::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+};
+#[cfg(any(kani, feature = "kani"))]
+use crate::vec::fast::Vec;
+#[cfg(not(any(kani, feature = "kani")))]
+use std::vec::Vec;
+
+/// The most data an account can hold.
+pub const MAX_PERMITTED_DATA_LENGTH: u64 = 10 * 1024 * 1024;
+
+/// Errors of the System program, returned as `ProgramError::Custom`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SystemError {
+    AccountAlreadyInUse,
+    ResultWithNegativeLamports,
+    InvalidProgramId,
+    InvalidAccountDataLength,
+    MaxSeedLengthExceeded,
+    AddressWithSeedMismatch,
+}
+
+impl From<SystemError> for ProgramError {
+    fn from(e: SystemError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+/// The instructions of the System program. The model encodes them with borsh
+/// rather than bincode.
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum SystemInstruction {
+    /// Accounts: `[writable, signer]` funding, `[writable, signer]` new.
+    CreateAccount {
+        lamports: u64,
+        space: u64,
+        owner: Pubkey,
+    },
+    /// Accounts: `[writable, signer]` assigned.
+    Assign { owner: Pubkey },
+    /// Accounts: `[writable, signer]` funding, `[writable]` recipient.
+    Transfer { lamports: u64 },
+    /// Accounts: `[writable, signer]` funding, `[writable]` created,
+    /// `[signer]` base if it isn't the funding account.
+    CreateAccountWithSeed {
+        base: Pubkey,
+        seed: String,
+        lamports: u64,
+        space: u64,
+        owner: Pubkey,
+    },
+    /// Accounts: `[writable, signer]` allocated.
+    Allocate { space: u64 },
+    /// Accounts: `[writable]` allocated, `[signer]` base.
+    AllocateWithSeed {
+        base: Pubkey,
+        seed: String,
+        space: u64,
+        owner: Pubkey,
+    },
+    /// Accounts: `[writable]` assigned, `[signer]` base.
+    AssignWithSeed {
+        base: Pubkey,
+        seed: String,
+        owner: Pubkey,
+    },
+    /// Accounts: `[writable]` funding, `[signer]` base, `[writable]`
+    /// recipient.
+    TransferWithSeed {
+        lamports: u64,
+        from_seed: String,
+        from_owner: Pubkey,
+    },
+}
+
+pub fn create_account(
+    from_pubkey: &Pubkey,
+    to_pubkey: &Pubkey,
+    lamports: u64,
+    space: u64,
+    owner: &Pubkey,
+) -> Instruction {
+    let account_metas = Vec::from([
+        AccountMeta::new(*from_pubkey, true),
+        AccountMeta::new(*to_pubkey, true),
+    ]);
+    Instruction::new_with_borsh(
+        system_program::id(),
+        &SystemInstruction::CreateAccount {
+            lamports,
+            space,
+            owner: *owner,
+        },
+        account_metas,
+    )
+}
+
+pub fn create_account_with_seed(
+    from_pubkey: &Pubkey,
+    to_pubkey: &Pubkey,
+    base: &Pubkey,
+    seed: &str,
+    lamports: u64,
+    space: u64,
+    owner: &Pubkey,
+) -> Instruction {
+    let mut account_metas = Vec::from([
+        AccountMeta::new(*from_pubkey, true),
+        AccountMeta::new(*to_pubkey, false),
+    ]);
+    if base != from_pubkey {
+        account_metas.push(AccountMeta::new_readonly(*base, true));
+    }
+    Instruction::new_with_borsh(
+        system_program::id(),
+        &SystemInstruction::CreateAccountWithSeed {
+            base: *base,
+            seed: seed.to_string(),
+            lamports,
+            space,
+            owner: *owner,
+        },
+        account_metas,
+    )
+}
+
+pub fn assign(pubkey: &Pubkey, owner: &Pubkey) -> Instruction {
+    let account_metas = Vec::from([AccountMeta::new(*pubkey, true)]);
+    Instruction::new_with_borsh(
+        system_program::id(),
+        &SystemInstruction::Assign { owner: *owner },
+        account_metas,
+    )
+}
+
+pub fn assign_with_seed(
+    address: &Pubkey,
+    base: &Pubkey,
+    seed: &str,
+    owner: &Pubkey,
+) -> Instruction {
+    let account_metas = Vec::from([
+        AccountMeta::new(*address, false),
+        AccountMeta::new_readonly(*base, true),
+    ]);
+    Instruction::new_with_borsh(
+        system_program::id(),
+        &SystemInstruction::AssignWithSeed {
+            base: *base,
+            seed: seed.to_string(),
+            owner: *owner,
+        },
+        account_metas,
+    )
+}
+
+pub fn transfer(from_pubkey: &Pubkey, to_pubkey: &Pubkey, lamports: u64) -> Instruction {
+    let account_metas = Vec::from([
+        AccountMeta::new(*from_pubkey, true),
+        AccountMeta::new(*to_pubkey, false),
+    ]);
+    Instruction::new_with_borsh(
+        system_program::id(),
+        &SystemInstruction::Transfer { lamports },
+        account_metas,
+    )
+}
+
+pub fn transfer_with_seed(
+    from_pubkey: &Pubkey,
+    from_base: &Pubkey,
+    from_seed: String,
+    from_owner: &Pubkey,
+    to_pubkey: &Pubkey,
+    lamports: u64,
+) -> Instruction {
+    let account_metas = Vec::from([
+        AccountMeta::new(*from_pubkey, false),
+        AccountMeta::new_readonly(*from_base, true),
+        AccountMeta::new(*to_pubkey, false),
+    ]);
+    Instruction::new_with_borsh(
+        system_program::id(),
+        &SystemInstruction::TransferWithSeed {
+            lamports,
+            from_seed,
+            from_owner: *from_owner,
+        },
+        account_metas,
+    )
+}
+
+pub fn allocate(pubkey: &Pubkey, space: u64) -> Instruction {
+    let account_metas = Vec::from([AccountMeta::new(*pubkey, true)]);
+    Instruction::new_with_borsh(
+        system_program::id(),
+        &SystemInstruction::Allocate { space },
+        account_metas,
+    )
+}
+
+pub fn allocate_with_seed(
+    address: &Pubkey,
+    base: &Pubkey,
+    seed: &str,
+    space: u64,
+    owner: &Pubkey,
+) -> Instruction {
+    let account_metas = Vec::from([
+        AccountMeta::new(*address, false),
+        AccountMeta::new_readonly(*base, true),
+    ]);
+    Instruction::new_with_borsh(
+        system_program::id(),
+        &SystemInstruction::AllocateWithSeed {
+            base: *base,
+            seed: seed.to_string(),
+            space,
+            owner: *owner,
+        },
+        account_metas,
+    )
 }
@@ -1,4 +1,14 @@
-use super::pubkey::Pubkey;
+use borsh::BorshDeserialize;
+
+use super::{
+    account_info::AccountInfo,
+    instruction::Instruction,
+    program::{CpiModel, ProgramResult},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_instruction::{SystemError, SystemInstruction, MAX_PERMITTED_DATA_LENGTH},
+};
+
 /// The static program ID.
 pub static ID: Pubkey = Pubkey { t: [0x42] };
 
@@ -11,3 +21,240 @@ pub fn check_id(id: &Pubkey) -> bool {
 pub fn id() -> Pubkey {
     ID
 }
+
+/// The model of the System program, run by `invoke` on System instructions.
+pub struct SystemProgram;
+
+impl CpiModel for SystemProgram {
+    fn process(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        process_instruction(instruction, account_infos)?;
+        Ok(())
+    }
+}
+
+/// An account of a System instruction, with whether the instruction has it
+/// sign, as the callee of a CPI sees it.
+struct Account<'b, 'a> {
+    info: &'b AccountInfo<'a>,
+    is_signer: bool,
+}
+
+/// Runs a System instruction on `account_infos`, like the System program.
+pub fn process_instruction(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+) -> Result<(), ProgramError> {
+    let account = |i: usize| {
+        let meta = instruction
+            .accounts
+            .get(i)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let info = account_infos
+            .iter()
+            .find(|info| *info.key == meta.pubkey)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        Ok::<_, ProgramError>(Account {
+            info,
+            is_signer: meta.is_signer,
+        })
+    };
+
+    let system_instruction = SystemInstruction::try_from_slice(&instruction.data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    match system_instruction {
+        SystemInstruction::CreateAccount {
+            lamports,
+            space,
+            owner,
+        } => create_account(&account(0)?, &account(1)?, lamports, space, &owner),
+        SystemInstruction::Assign { owner } => assign(&account(0)?, &owner),
+        SystemInstruction::Transfer { lamports } => transfer(&account(0)?, &account(1)?, lamports),
+        SystemInstruction::CreateAccountWithSeed {
+            base,
+            seed,
+            lamports,
+            space,
+            owner,
+        } => {
+            let from = account(0)?;
+            let to = account(1)?;
+            let base_signed = if instruction.accounts.len() > 2 {
+                account(2)?.is_signer
+            } else {
+                from.is_signer
+            };
+            let to = with_seed(to, &base, base_signed, &seed, &owner)?;
+            create_account(&from, &to, lamports, space, &owner)
+        }
+        SystemInstruction::Allocate { space } => allocate(&account(0)?, space),
+        SystemInstruction::AllocateWithSeed {
+            base,
+            seed,
+            space,
+            owner,
+        } => {
+            let account = with_seed(account(0)?, &base, account(1)?.is_signer, &seed, &owner)?;
+            allocate(&account, space)?;
+            assign(&account, &owner)
+        }
+        SystemInstruction::AssignWithSeed { base, seed, owner } => {
+            let account = with_seed(account(0)?, &base, account(1)?.is_signer, &seed, &owner)?;
+            assign(&account, &owner)
+        }
+        SystemInstruction::TransferWithSeed {
+            lamports,
+            from_seed,
+            from_owner,
+        } => {
+            let base = account(1)?;
+            let from = with_seed(
+                account(0)?,
+                base.info.key,
+                base.is_signer,
+                &from_seed,
+                &from_owner,
+            )?;
+            transfer(&from, &account(2)?, lamports)
+        }
+    }
+}
+
+/// Checks `account` is derived from `base` and `seed`, and makes it sign when
+/// `base` does.
+fn with_seed<'b, 'a>(
+    account: Account<'b, 'a>,
+    base: &Pubkey,
+    base_signed: bool,
+    seed: &str,
+    owner: &Pubkey,
+) -> Result<Account<'b, 'a>, ProgramError> {
+    let address = Pubkey::create_with_seed(base, seed, owner)
+        .map_err(|_| SystemError::MaxSeedLengthExceeded)?;
+    if address != *account.info.key {
+        return Err(SystemError::AddressWithSeedMismatch.into());
+    }
+    Ok(Account {
+        info: account.info,
+        is_signer: base_signed,
+    })
+}
+
+/// Checks `from` can fund a transfer of `lamports`.
+fn check_transfer(from: &Account, lamports: u64) -> Result<(), ProgramError> {
+    if !from.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !check_id(from.info.owner) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if !from.info.data.is_empty() {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if lamports > from.info.lamports() {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    Ok(())
+}
+
+fn transfer(from: &Account, to: &Account, lamports: u64) -> Result<(), ProgramError> {
+    check_transfer(from, lamports)?;
+    let balance = to
+        .info
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ProgramError::InvalidArgument)?;
+
+    **from.info.lamports.borrow_mut() -= lamports;
+    **to.info.lamports.borrow_mut() = balance;
+    Ok(())
+}
+
+fn allocate(account: &Account, space: u64) -> Result<(), ProgramError> {
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !account.info.data.is_empty() || !check_id(account.info.owner) {
+        return Err(SystemError::AccountAlreadyInUse.into());
+    }
+    if space > MAX_PERMITTED_DATA_LENGTH {
+        return Err(SystemError::InvalidAccountDataLength.into());
+    }
+
+    account
+        .info
+        .realloc(space as usize, true)
+        .map_err(|_| ProgramError::InvalidRealloc)
+}
+
+fn assign(account: &Account, owner: &Pubkey) -> Result<(), ProgramError> {
+    if account.info.owner == owner {
+        return Ok(());
+    }
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !check_id(account.info.owner) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    account.info.assign(owner);
+    Ok(())
+}
+
+fn create_account(
+    from: &Account,
+    to: &Account,
+    lamports: u64,
+    space: u64,
+    owner: &Pubkey,
+) -> Result<(), ProgramError> {
+    if to.info.lamports() > 0 {
+        return Err(SystemError::AccountAlreadyInUse.into());
+    }
+    // A failed instruction leaves the accounts unchanged, so the funding
+    // account is checked before the new one is allocated.
+    check_transfer(from, lamports)?;
+
+    allocate(to, space)?;
+    assign(to, owner)?;
+    transfer(from, to, lamports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_instruction;
+
+    #[test]
+    fn test_create_account() {
+        let (from_key, to_key, owner) = (Pubkey { t: [1] }, Pubkey { t: [2] }, Pubkey { t: [3] });
+        let from = AccountInfo::new(&from_key, true, true, 10, 0, ID);
+        let to = AccountInfo::new(&to_key, true, true, 0, 0, ID);
+
+        let ix = system_instruction::create_account(&from_key, &to_key, 20, 8, &owner);
+        assert_eq!(
+            process_instruction(&ix, &[from, to]),
+            Err(ProgramError::InsufficientFunds)
+        );
+        assert_eq!(to.data_len(), 0);
+
+        let ix = system_instruction::create_account(&from_key, &to_key, 4, 8, &owner);
+        assert_eq!(process_instruction(&ix, &[from, to]), Ok(()));
+        assert_eq!(from.lamports(), 6);
+        assert_eq!(to.lamports(), 4);
+        assert_eq!(to.data_len(), 8);
+        assert_eq!(*to.owner, owner);
+        assert_eq!(*from.owner, ID);
+
+        let ix = system_instruction::create_account(&from_key, &to_key, 4, 8, &owner);
+        assert_eq!(
+            process_instruction(&ix, &[from, to]),
+            Err(SystemError::AccountAlreadyInUse.into())
+        );
+    }
+}
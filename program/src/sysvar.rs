@@ -1,32 +1,152 @@
 use crate::account_info::AccountInfo;
 use crate::program_error::ProgramError;
+use crate::pubkey::Pubkey;
 
 pub mod rent {
-    use super::Sysvar;
+    use super::{Sysvar, SysvarId};
     use crate::pubkey::Pubkey;
     pub use crate::rent::Rent;
 
     impl Sysvar for Rent {}
+    impl SysvarId for Rent {
+        fn id() -> Pubkey {
+            id()
+        }
+    }
     pub fn id() -> Pubkey {
         Pubkey { t: [43] }
     }
+    pub fn check_id(id: &Pubkey) -> bool {
+        *id == self::id()
+    }
+}
+
+pub mod clock {
+    use super::{Sysvar, SysvarId};
+    pub use crate::clock::Clock;
+    use crate::pubkey::Pubkey;
+
+    impl Sysvar for Clock {}
+    impl SysvarId for Clock {
+        fn id() -> Pubkey {
+            id()
+        }
+    }
+    pub fn id() -> Pubkey {
+        Pubkey { t: [44] }
+    }
+    pub fn check_id(id: &Pubkey) -> bool {
+        *id == self::id()
+    }
+}
+
+/// The instructions sysvar, holding the instructions of the transaction.
+/// Their contents aren't modeled: loading one gives an arbitrary one.
+pub mod instructions {
+    use crate::pubkey::Pubkey;
+    #[cfg(any(kani, feature = "kani"))]
+    use crate::{account_info::AccountInfo, instruction::Instruction, program_error::ProgramError};
+
+    pub fn id() -> Pubkey {
+        Pubkey { t: [45] }
+    }
+    pub fn check_id(id: &Pubkey) -> bool {
+        *id == self::id()
+    }
+
+    #[cfg(any(kani, feature = "kani"))]
+    pub fn load_current_index_checked(info: &AccountInfo) -> Result<u16, ProgramError> {
+        if !check_id(info.key) {
+            return Err(ProgramError::UnsupportedSysvar);
+        }
+        Ok(kani::any())
+    }
+
+    #[cfg(any(kani, feature = "kani"))]
+    pub fn load_instruction_at_checked(
+        _index: usize,
+        info: &AccountInfo,
+    ) -> Result<Instruction, ProgramError> {
+        if !check_id(info.key) {
+            return Err(ProgramError::UnsupportedSysvar);
+        }
+        Ok(Instruction {
+            program_id: kani::any(),
+            ..Default::default()
+        })
+    }
+
+    /// Reads the data of an account as the instructions sysvar, without
+    /// checking it is, so any account can be passed in its place.
+    #[cfg(any(kani, feature = "kani"))]
+    pub fn load_current_index<D: ?Sized>(_data: &D) -> u16 {
+        super::spoofing::mark_unchecked_read();
+        kani::any()
+    }
+
+    /// Same as `load_current_index`.
+    #[cfg(any(kani, feature = "kani"))]
+    pub fn load_instruction_at<D: ?Sized>(
+        _index: usize,
+        _data: &D,
+    ) -> Result<Instruction, ProgramError> {
+        super::spoofing::mark_unchecked_read();
+        Ok(Instruction {
+            program_id: kani::any(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Tracks reads of sysvar data that didn't check the key of the account.
+#[cfg(any(kani, feature = "kani"))]
+mod spoofing {
+    static mut UNCHECKED_READ: bool = false;
+
+    pub(crate) fn mark_unchecked_read() {
+        unsafe { UNCHECKED_READ = true };
+    }
+
+    pub fn unchecked_sysvar_read() -> bool {
+        unsafe { UNCHECKED_READ }
+    }
+}
+
+#[cfg(any(kani, feature = "kani"))]
+pub(crate) use spoofing::mark_unchecked_read;
+#[cfg(any(kani, feature = "kani"))]
+pub use spoofing::unchecked_sysvar_read;
+
+/// The id of the sysvar account holding a sysvar.
+pub trait SysvarId {
+    fn id() -> Pubkey;
+
+    fn check_id(id: &Pubkey) -> bool {
+        *id == Self::id()
+    }
 }
 
 // note: this is different than Sysvar struct from anchor
-pub trait Sysvar: Sized {
+pub trait Sysvar: SysvarId + Sized {
     // Provided methods
     // fn size_of() -> usize;
 
     #[cfg(not(any(kani, feature = "kani")))]
-    fn from_account_info(_account_info: &AccountInfo<'_>) -> Result<Self, ProgramError> {
+    fn from_account_info(account_info: &AccountInfo<'_>) -> Result<Self, ProgramError> {
+        if !Self::check_id(account_info.key) {
+            return Err(ProgramError::UnsupportedSysvar);
+        }
         panic!("not impl")
     }
 
     #[cfg(any(kani, feature = "kani"))]
-    fn from_account_info(_account_info: &AccountInfo<'_>) -> Result<Self, ProgramError>
+    fn from_account_info(account_info: &AccountInfo<'_>) -> Result<Self, ProgramError>
     where
         Self: kani::Arbitrary,
     {
+        if !Self::check_id(account_info.key) {
+            return Err(ProgramError::UnsupportedSysvar);
+        }
         Self::get()
     }
 
@@ -39,7 +159,6 @@ pub trait Sysvar: Sized {
     where
         Self: kani::Arbitrary,
     {
-        // TODO(ahaberlandt): id check is performed here
         // XXX: Maybe we should reason about failures? prob not useful
         Ok(kani::any())
     }
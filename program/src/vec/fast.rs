@@ -297,3 +297,25 @@ impl<T: kani::Arbitrary + Default> kani::Arbitrary for Vec<T> {
         v
     }
 }
+
+#[cfg(any(kani, feature = "kani"))]
+impl<T: Default> Vec<T> {
+    /// Generates a vector of at most `max_len` elements made by `f`. Unlike
+    /// `kani::any`, this doesn't loop a symbolic number of times.
+    pub fn any_bounded_with(max_len: usize, mut f: impl FnMut() -> T) -> Self {
+        let size: usize = kani::any();
+        kani::assume(size <= max_len && size <= VEC_SIZE);
+        Vec {
+            data: std::array::from_fn(|i| if i < size { f() } else { T::default() }),
+            size,
+        }
+    }
+
+    /// Generates a vector of at most `max_len` arbitrary elements.
+    pub fn any_bounded(max_len: usize) -> Self
+    where
+        T: kani::Arbitrary,
+    {
+        Self::any_bounded_with(max_len, kani::any)
+    }
+}
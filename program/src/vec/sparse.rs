@@ -83,6 +83,7 @@ impl<T> Vec<T> {
     pub fn as_slice(&self) -> SparseSlice<T> {
         SparseSlice::<T> {
             len: self.size,
+            origin: self as *const Self as usize,
             t: Default::default(),
         }
     }
@@ -155,9 +156,13 @@ impl<T: kani::Arbitrary + Default> kani::Arbitrary for Vec<T> {
 // this is checked by the type checker! This serves as a cheap
 // way to ensure that the account data is only used for packing
 // and unpacking.
+//
+// - Instead of data, a slice remembers the address of the vec it was
+// taken from, which tells the pack model which account it is packing.
 
 pub struct SparseSlice<T> {
     pub len: usize,
+    pub origin: usize,
     pub t: PhantomData<T>,
 }
 
@@ -168,6 +173,9 @@ impl<T> SparseSlice<T> {
     pub fn len(&self) -> usize {
         self.len
     }
+    pub fn origin(&self) -> usize {
+        self.origin
+    }
 }
 
 impl<T> Index<Range<usize>> for SparseSlice<T> {